@@ -0,0 +1,463 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use chrono::Weekday;
+use ratatui::style::Color;
+use ratatui::style::Style;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+use structopt::StructOpt;
+
+use crate::db::NodeID;
+use crate::keys::KeysConfig;
+use crate::output;
+
+const DEFAULT_GRAPH: &str = "graph";
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Where the default graph lives.
+    pub database: Option<PathBuf>,
+    /// Locations of named graphs, selected with `--graph <name>`.
+    /// Graphs which aren't listed here live next to the default graph.
+    pub graphs: BTreeMap<String, PathBuf>,
+    /// Command used by `tasq edit`. Falls back to `$EDITOR`, and then `vi`.
+    pub editor: Option<String>,
+    pub week_start: Weekday,
+    /// Output format used when `--output` isn't passed.
+    pub output: Option<output::Format>,
+    pub next: NextConfig,
+    pub theme: Theme,
+    /// Keybindings for `tasq ui`.
+    pub keys: KeysConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            database: None,
+            graphs: BTreeMap::new(),
+            editor: None,
+            week_start: Weekday::Mon,
+            output: None,
+            next: NextConfig::default(),
+            theme: Theme::default(),
+            keys: KeysConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NextConfig {
+    /// Root used by `tasq next` when `--root` isn't passed.
+    pub root: Option<NodeID>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    pub border: ThemeColor,
+    pub highlight: ThemeColor,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            border: ThemeColor(Color::Reset),
+            highlight: ThemeColor(Color::Reset),
+        }
+    }
+}
+
+impl Theme {
+    pub fn border_style(&self) -> Style {
+        Style::default().fg(self.border.0)
+    }
+
+    pub fn highlight_style(&self) -> Style {
+        Style::default().fg(self.highlight.0)
+    }
+}
+
+/// A terminal color, written in the config as a name (`"light-blue"`),
+/// a hex code (`"#ff8800"`), or a 256-color index (`"208"`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThemeColor(pub Color);
+
+const COLOR_NAMES: [(&str, Color); 17] = [
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("red", Color::Red),
+    ("green", Color::Green),
+    ("yellow", Color::Yellow),
+    ("blue", Color::Blue),
+    ("magenta", Color::Magenta),
+    ("cyan", Color::Cyan),
+    ("gray", Color::Gray),
+    ("dark-gray", Color::DarkGray),
+    ("light-red", Color::LightRed),
+    ("light-green", Color::LightGreen),
+    ("light-yellow", Color::LightYellow),
+    ("light-blue", Color::LightBlue),
+    ("light-magenta", Color::LightMagenta),
+    ("light-cyan", Color::LightCyan),
+    ("white", Color::White),
+];
+
+impl FromStr for ThemeColor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_lowercase();
+        if let Some((_, color)) = COLOR_NAMES.iter().find(|(name, _)| *name == lower) {
+            return Ok(ThemeColor(*color));
+        }
+        if let Some(hex) = lower.strip_prefix('#') {
+            if hex.len() == 6 {
+                if let Ok(rgb) = u32::from_str_radix(hex, 16) {
+                    let [_, r, g, b] = rgb.to_be_bytes();
+                    return Ok(ThemeColor(Color::Rgb(r, g, b)));
+                }
+            }
+        }
+        if let Ok(index) = lower.parse::<u8>() {
+            return Ok(ThemeColor(Color::Indexed(index)));
+        }
+        Err(anyhow!("Unknown color {:?}", s))
+    }
+}
+
+impl fmt::Display for ThemeColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((name, _)) = COLOR_NAMES.iter().find(|(_, color)| *color == self.0) {
+            return write!(f, "{}", name);
+        }
+        match self.0 {
+            Color::Rgb(r, g, b) => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Indexed(index) => write!(f, "{}", index),
+            _ => unreachable!("Every other color has a name"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for ThemeColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl Config {
+    pub fn load() -> anyhow::Result<Self> {
+        let path = config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = std::fs::read_to_string(&path)?;
+        Self::parse(&contents).map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Parses a config file.
+    /// Errors from here include the line and column of the offending value.
+    fn parse(contents: &str) -> anyhow::Result<Self> {
+        Ok(toml::from_str(contents)?)
+    }
+
+    /// Command to launch an editor with, split into the program and its arguments.
+    /// This is split the way a shell would, so paths with spaces can be quoted,
+    /// though a path to an editor also works as is.
+    pub fn editor_command(&self) -> anyhow::Result<Vec<String>> {
+        let editor = match &self.editor {
+            Some(editor) => editor.clone(),
+            None => std::env::var("EDITOR").unwrap_or_else(|_| "vi".to_string()),
+        };
+        if Path::new(&editor).is_file() {
+            return Ok(vec![editor]);
+        }
+        let mut command = shell_words::split(&editor)
+            .map_err(|e| anyhow!("Couldn't parse editor command {:?}: {}", editor, e))?;
+        if command.is_empty() {
+            command.push("vi".to_string());
+        }
+        Ok(command)
+    }
+
+    /// Picks the database to open, preferring (in order):
+    ///
+    /// - an explicit `--db <path>`,
+    /// - a named graph from `--graph <name>`,
+    /// - the `TASQ_DB` environment variable,
+    /// - `database` from the config file,
+    /// - `$XDG_DATA_HOME/tasq/graph.sqlite3`.
+    pub fn database_path(
+        &self,
+        db: Option<PathBuf>,
+        graph: Option<&str>,
+    ) -> anyhow::Result<PathBuf> {
+        if let Some(db) = db {
+            return Ok(db);
+        }
+        if let Some(graph) = graph {
+            if let Some(path) = self.graphs.get(graph) {
+                return Ok(path.clone());
+            }
+            return graph_path(graph);
+        }
+        if let Some(db) = std::env::var_os("TASQ_DB") {
+            return Ok(PathBuf::from(db));
+        }
+        if let Some(database) = &self.database {
+            return Ok(database.clone());
+        }
+        graph_path(DEFAULT_GRAPH)
+    }
+}
+
+#[derive(Debug, StructOpt)]
+pub enum Args {
+    /// Print where the config file lives.
+    Path,
+    /// Print a config value, e.g. `tasq config get theme.border`.
+    Get { key: String },
+    /// Set a config value, e.g. `tasq config set week_start sunday`.
+    Set { key: String, value: String },
+}
+
+pub fn main(args: Args) -> anyhow::Result<()> {
+    match args {
+        Args::Path => println!("{}", config_path()?.display()),
+        Args::Get { key } => println!("{}", get(&Config::load()?, &key)?),
+        Args::Set { key, value } => set(&key, &value)?,
+    }
+    Ok(())
+}
+
+fn get(config: &Config, key: &str) -> anyhow::Result<String> {
+    let mut value = toml::Value::try_from(config)?;
+    for part in key.split('.') {
+        value = match value {
+            toml::Value::Table(mut table) => table
+                .remove(part)
+                .ok_or_else(|| anyhow!("{} is not set", key))?,
+            _ => return Err(anyhow!("Unknown config key {}", key)),
+        };
+    }
+
+    match value {
+        toml::Value::String(s) => Ok(s),
+        toml::Value::Table(table) => Ok(toml::to_string(&table)?.trim_end().to_string()),
+        value => Ok(value.to_string()),
+    }
+}
+
+fn set(key: &str, value: &str) -> anyhow::Result<()> {
+    let path = config_path()?;
+    let contents = if path.exists() {
+        std::fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+
+    let contents = set_in(&contents, key, value)?;
+    // Refuse to write a config that we won't be able to load later.
+    Config::parse(&contents).map_err(|e| anyhow!("Can't set {} to {:?}: {}", key, value, e))?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, contents)?;
+    Ok(())
+}
+
+/// Sets `key` in the TOML document `contents`, leaving the rest of its formatting alone.
+/// Values are parsed as TOML when possible (so `true` and `3` keep their types),
+/// and are otherwise treated as bare strings.
+fn set_in(contents: &str, key: &str, value: &str) -> anyhow::Result<String> {
+    let mut document: toml_edit::Document = contents.parse()?;
+    let value = value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| toml_edit::Value::from(value));
+
+    let parts: Vec<&str> = key.split('.').collect();
+    let Some((last, tables)) = parts.split_last() else {
+        return Err(anyhow!("Empty config key"));
+    };
+    let mut item = document.as_item_mut();
+    for table in tables.iter() {
+        item = &mut item[table];
+        if item.is_none() {
+            *item = toml_edit::table();
+        }
+        if !item.is_table_like() {
+            return Err(anyhow!("{} is not a table", table));
+        }
+    }
+    item[last] = toml_edit::value(value);
+
+    Ok(document.to_string())
+}
+
+pub fn config_path() -> anyhow::Result<PathBuf> {
+    let mut path = xdg_dir("XDG_CONFIG_HOME", ".config")?;
+    path.push("tasq");
+    path.push("config.toml");
+    Ok(path)
+}
+
+fn graph_path(name: &str) -> anyhow::Result<PathBuf> {
+    if name.is_empty() || name.contains(std::path::is_separator) {
+        return Err(anyhow!("Invalid graph name {:?}", name));
+    }
+
+    let mut path = xdg_dir("XDG_DATA_HOME", ".local/share")?;
+    path.push("tasq");
+    path.push(format!("{}.sqlite3", name));
+    Ok(path)
+}
+
+fn xdg_dir(var: &str, home_fallback: &str) -> anyhow::Result<PathBuf> {
+    // The XDG spec says to ignore relative paths.
+    if let Some(dir) = std::env::var_os(var).map(PathBuf::from) {
+        if dir.is_absolute() {
+            return Ok(dir);
+        }
+    }
+
+    let Some(home) = std::env::var_os("HOME") else {
+        return Err(anyhow!("Neither ${} nor $HOME is set", var));
+    };
+    let mut dir = PathBuf::from(home);
+    dir.push(home_fallback);
+    Ok(dir)
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_database_path__explicit_db_wins() {
+        let config = Config {
+            database: Some(PathBuf::from("/from/config.sqlite3")),
+            ..Default::default()
+        };
+        let path = config
+            .database_path(Some(PathBuf::from("/explicit.sqlite3")), Some("work"))
+            .unwrap();
+        assert_eq!(path, PathBuf::from("/explicit.sqlite3"));
+    }
+
+    #[test]
+    fn test_database_path__configured_graph() {
+        let mut config = Config::default();
+        config
+            .graphs
+            .insert("work".to_string(), PathBuf::from("/work.sqlite3"));
+        let path = config.database_path(None, Some("work")).unwrap();
+        assert_eq!(path, PathBuf::from("/work.sqlite3"));
+    }
+
+    #[test]
+    fn test_graph_path__rejects_separators() {
+        assert!(graph_path("../work").is_err());
+        assert!(graph_path("").is_err());
+    }
+
+    #[test]
+    fn test_load__parses_graphs() {
+        let config = Config::parse(
+            r#"
+            database = "/home/me/tasq.sqlite3"
+
+            [graphs]
+            work = "/home/me/work.sqlite3"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.database,
+            Some(PathBuf::from("/home/me/tasq.sqlite3"))
+        );
+        assert_eq!(
+            config.graphs.get("work"),
+            Some(&PathBuf::from("/home/me/work.sqlite3"))
+        );
+    }
+
+    #[test]
+    fn test_parse__reports_line() {
+        let error = Config::parse("editor = \"nvim\"\nweek_start = \"someday\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 2"), "{}", error);
+    }
+
+    #[test]
+    fn test_parse__rejects_unknown_keys() {
+        let error = Config::parse("[theme]\nbordr = \"red\"\n")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("line 2"), "{}", error);
+    }
+
+    #[test]
+    fn test_theme_color__round_trip() {
+        for color in ["light-blue", "#ff8800", "208"] {
+            let parsed: ThemeColor = color.parse().unwrap();
+            assert_eq!(parsed.to_string(), color);
+        }
+        assert!("not-a-color".parse::<ThemeColor>().is_err());
+    }
+
+    #[test]
+    fn test_get__nested_key() {
+        let config = Config::parse("[theme]\nborder = \"red\"\n").unwrap();
+        assert_eq!(get(&config, "theme.border").unwrap(), "red");
+        assert_eq!(get(&config, "week_start").unwrap(), "Mon");
+        assert!(get(&config, "editor").is_err());
+    }
+
+    #[test]
+    fn test_set_in__preserves_formatting() {
+        let contents = "# my editor\neditor = \"nvim\"\n";
+        let contents = set_in(contents, "theme.border", "red").unwrap();
+        assert!(contents.starts_with("# my editor\neditor = \"nvim\"\n"));
+        let config = Config::parse(&contents).unwrap();
+        assert_eq!(config.theme.border, ThemeColor(Color::Red));
+    }
+    #[test]
+    fn test_editor_command__paths_with_spaces() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let editor = temp_dir.path().join("Sublime Text");
+        std::fs::write(&editor, "").unwrap();
+        let editor = editor.to_string_lossy().to_string();
+
+        let mut config = Config {
+            editor: Some(editor.clone()),
+            ..Config::default()
+        };
+        assert_eq!(config.editor_command().unwrap(), vec![editor.clone()]);
+
+        config.editor = Some(format!("'{}' --wait", editor));
+        assert_eq!(
+            config.editor_command().unwrap(),
+            vec![editor, "--wait".to_string()]
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::collections::BTreeSet;
 use std::fs::File;
 use std::path::Path;
@@ -5,12 +6,17 @@ use std::path::Path;
 use anyhow::anyhow;
 use chrono::serde::ts_seconds_option;
 use chrono::DateTime;
+use chrono::Duration;
 use chrono::LocalResult;
 use chrono::TimeZone;
 use chrono::Utc;
 use serde::Deserialize;
 use serde::Serialize;
+use sqlx::query::Query;
+use sqlx::sqlite::Sqlite;
+use sqlx::sqlite::SqliteArguments;
 use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::sqlite::SqliteConnection;
 use sqlx::sqlite::SqlitePool;
 use sqlx::sqlite::SqlitePoolOptions;
 use sqlx::sqlite::SqliteRow;
@@ -22,30 +28,67 @@ pub struct Database {
 }
 
 impl Database {
-    pub async fn default_new() -> anyhow::Result<Self> {
-        let mut graph_file = std::env::current_dir()?;
-        graph_file.push("graph.sqlite3");
-        Self::new(&graph_file).await
+    /// Opens an existing database.
+    /// This refuses to create one so that running from the wrong place
+    /// doesn't quietly hand back an empty graph; see [`Database::create`].
+    pub async fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Err(anyhow!(
+                "No database at {}; run `tasq init` to create one",
+                path.display()
+            ));
+        }
+        Self::connect_to(path).await
     }
 
-    pub async fn new(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+    pub async fn create(path: impl AsRef<Path>) -> anyhow::Result<Self> {
         let path = path.as_ref();
-        let mut create_tables = false;
-        if !path.exists() {
-            create_tables = true;
-            File::create(path)?;
+        if path.exists() {
+            return Err(anyhow!("Database already exists at {}", path.display()));
         }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        File::create(path)?;
 
+        match Self::set_up(path).await {
+            Ok(db) => Ok(db),
+            Err(e) => {
+                // Otherwise the half made file would stop `tasq init` from being tried again.
+                let _ = std::fs::remove_file(path);
+                Err(e)
+            }
+        }
+    }
+
+    async fn set_up(path: &Path) -> anyhow::Result<Self> {
+        let db = Self::connect_to(path).await?;
+        db.create_nodes_table().await?;
+        db.create_edges_table().await?;
+        Ok(db)
+    }
+
+    async fn connect_to(path: &Path) -> anyhow::Result<Self> {
+        // A single connection keeps `PRAGMA data_version` meaningful:
+        // it only moves when some _other_ connection writes to the database.
         let pool = SqlitePoolOptions::new()
+            .max_connections(1)
             .connect_with(SqliteConnectOptions::new().filename(path))
             .await?;
-
         let db = Self { pool };
-        if create_tables {
-            db.create_nodes_table().await?;
-            db.create_edges_table().await?;
-        }
-
+        // Unlike the graph itself, these are safe to create on the fly
+        // so that databases made before it existed keep working.
+        db.create_expanded_table().await?;
+        db.create_inbox_table().await?;
+        db.create_reviewed_table().await?;
+        db.create_time_entries_table().await?;
+        // Databases made before edges had kinds only have containment edges,
+        // which is what the new column defaults to.
+        db.add_column("edges", "kind", "TEXT NOT NULL DEFAULT 'contains'")
+            .await?;
+        db.add_column("nodes", "estimate", "INTEGER").await?;
+        db.rekey_edges().await?;
         Ok(db)
     }
 
@@ -65,48 +108,200 @@ impl Database {
         Ok(())
     }
 
+    /// Adds a column to a table made before the column existed.
+    async fn add_column(&self, table: &str, column: &str, definition: &str) -> anyhow::Result<()> {
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&mut self.pool.acquire().await?)
+            .await?;
+        // New databases are connected to before their tables are created.
+        if columns.is_empty() {
+            return Ok(());
+        }
+        if columns
+            .iter()
+            .any(|row| row.get::<String, _>("name") == column)
+        {
+            return Ok(());
+        }
+        sqlx::query(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {}",
+            table, column, definition
+        ))
+        .execute(&mut self.pool.acquire().await?)
+        .await?;
+        Ok(())
+    }
+
+    /// Rebuilds an edges table made before edges had kinds,
+    /// whose primary key stops a node from both containing and blocking another.
+    async fn rekey_edges(&self) -> anyhow::Result<()> {
+        let columns = sqlx::query("PRAGMA table_info(edges)")
+            .fetch_all(&mut self.pool.acquire().await?)
+            .await?;
+        let keyed = columns
+            .iter()
+            .any(|row| row.get::<String, _>("name") == "kind" && row.get::<i64, _>("pk") > 0);
+        if columns.is_empty() || keyed {
+            return Ok(());
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("ALTER TABLE edges RENAME TO old_edges")
+            .execute(&mut transaction)
+            .await?;
+        sqlx::query(std::include_str!("sql/create_edges.sql"))
+            .execute(&mut transaction)
+            .await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO edges (from_uuid, to_uuid, kind) \
+             SELECT from_uuid, to_uuid, kind FROM old_edges",
+        )
+        .execute(&mut transaction)
+        .await?;
+        sqlx::query("DROP TABLE old_edges")
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    async fn create_expanded_table(&self) -> anyhow::Result<()> {
+        let contents = std::include_str!("sql/create_expanded.sql");
+        sqlx::query(contents)
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_inbox_table(&self) -> anyhow::Result<()> {
+        let contents = std::include_str!("sql/create_inbox.sql");
+        sqlx::query(contents)
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_reviewed_table(&self) -> anyhow::Result<()> {
+        let contents = std::include_str!("sql/create_reviewed.sql");
+        sqlx::query(contents)
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    async fn create_time_entries_table(&self) -> anyhow::Result<()> {
+        let contents = std::include_str!("sql/create_time_entries.sql");
+        sqlx::query(contents)
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
     pub async fn add(&self, node: &Node) -> anyhow::Result<()> {
-        let query_str = std::include_str!("sql/insert_node.sql");
-        let query = sqlx::query(query_str)
-            .bind(node.id.to_string())
-            .bind(&node.title)
-            .bind(&node.description)
-            .bind(node.scheduled.map(|dt| dt.timestamp_millis()))
-            .bind(node.due.map(|dt| dt.timestamp_millis()))
-            .bind(node.completed.map(|dt| dt.timestamp_millis()))
-            .bind(node.trashed);
-        query.execute(&mut self.pool.acquire().await?).await?;
+        insert_query(node)
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
         Ok(())
     }
 
     pub async fn update(&self, node: &Node) -> anyhow::Result<()> {
         self.exists_check(&node.id).await?;
+        update_query(node)
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(())
+    }
 
-        let query_str = std::include_str!("sql/update_node.sql");
-        let query = sqlx::query(query_str)
-            .bind(&node.title)
-            .bind(&node.description)
-            .bind(node.scheduled.map(|dt| dt.timestamp_millis()))
-            .bind(node.due.map(|dt| dt.timestamp_millis()))
-            .bind(node.completed.map(|dt| dt.timestamp_millis()))
-            .bind(node.trashed)
-            .bind(node.id.to_string());
-        query.execute(&mut self.pool.acquire().await?).await?;
+    /// Makes every change in `changes` or, if any of them fails, none of them.
+    /// Edges are checked for cycles as they're made, so edges from earlier in the batch count.
+    pub async fn apply(&self, changes: &[Change]) -> anyhow::Result<()> {
+        let added: BTreeSet<NodeID> = changes
+            .iter()
+            .filter_map(|change| match change {
+                Change::Add(node) => Some(node.id),
+                _ => None,
+            })
+            .collect();
+
+        // These have to happen up front since the transaction holds the only connection.
+        for change in changes.iter() {
+            let ids = match change {
+                Change::Add(_) | Change::Disconnect(_, _) => vec![],
+                Change::Update(node) => vec![node.id],
+                Change::Connect(from, to) | Change::Block(from, to) => vec![*from, *to],
+                Change::Unblock(_, _) => vec![],
+            };
+            for id in ids.iter().filter(|id| !added.contains(id)) {
+                self.exists_check(id).await?;
+            }
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        for change in changes.iter() {
+            if let Change::Connect(from, to) | Change::Block(from, to) = change {
+                // Returning drops the transaction, which rolls back what's been done so far.
+                if creates_cycle(&mut transaction, *from, *to).await? {
+                    return Err(anyhow!(
+                        "Connecting {} to {} would create a cycle",
+                        from,
+                        to
+                    ));
+                }
+            }
+            let query = match change {
+                Change::Add(node) => insert_query(node),
+                Change::Update(node) => update_query(node),
+                Change::Connect(from, to) => connect_query(*from, *to, EdgeKind::Contains),
+                Change::Disconnect(from, to) => disconnect_query(*from, *to, EdgeKind::Contains),
+                Change::Block(from, to) => connect_query(*from, *to, EdgeKind::Blocks),
+                Change::Unblock(from, to) => disconnect_query(*from, *to, EdgeKind::Blocks),
+            };
+            query.execute(&mut transaction).await?;
+        }
+        transaction.commit().await?;
         Ok(())
     }
 
     pub async fn connect(&self, from: NodeID, to: NodeID) -> anyhow::Result<()> {
+        self.connect_with_kind(from, to, EdgeKind::Contains).await
+    }
+
+    /// Records that `blocked` can't be started until `blocker` is done.
+    pub async fn block(&self, blocker: NodeID, blocked: NodeID) -> anyhow::Result<()> {
+        self.connect_with_kind(blocker, blocked, EdgeKind::Blocks)
+            .await
+    }
+
+    async fn connect_with_kind(
+        &self,
+        from: NodeID,
+        to: NodeID,
+        kind: EdgeKind,
+    ) -> anyhow::Result<()> {
         self.exists_check(&from).await?;
         self.exists_check(&to).await?;
+        if self.would_create_cycle(from, to).await? {
+            return Err(anyhow!(
+                "Connecting {} to {} would create a cycle",
+                from,
+                to
+            ));
+        }
 
-        let query_str = std::include_str!("sql/connect_nodes.sql");
-        let query = sqlx::query(query_str)
-            .bind(from.to_string())
-            .bind(to.to_string());
-        query.execute(&mut self.pool.acquire().await?).await?;
+        connect_query(from, to, kind)
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
         Ok(())
     }
 
+    /// Checks whether adding an edge from `from` to `to` would stop the graph from being a DAG,
+    /// which happens when `from` is already reachable from `to`.
+    /// Edges of every kind count, since a task can't wait on something that's waiting on it.
+    pub async fn would_create_cycle(&self, from: NodeID, to: NodeID) -> anyhow::Result<bool> {
+        let mut connection = self.pool.acquire().await?;
+        creates_cycle(&mut connection, from, to).await
+    }
+
     pub async fn get_node(&self, id: NodeID) -> anyhow::Result<Node> {
         let row = sqlx::query("SELECT * FROM nodes WHERE uuid = ?")
             .bind(id.to_string())
@@ -129,30 +324,145 @@ impl Database {
         Ok(node_ids)
     }
 
-    pub async fn has_children(&self, id: NodeID) -> anyhow::Result<bool> {
-        let count = sqlx::query("SELECT COUNT(*) FROM edges WHERE from_uuid = ?")
-            .bind(id.to_string())
-            .fetch_one(&mut self.pool.acquire().await?)
+    /// Finds nodes whose uuid starts with `prefix`.
+    /// Callers must make sure `prefix` doesn't contain `LIKE` wildcards.
+    pub async fn get_nodes_by_id_prefix(&self, prefix: &str) -> anyhow::Result<Vec<NodeID>> {
+        let query_str = std::include_str!("sql/get_nodes_by_id_prefix.sql");
+        let rows = sqlx::query(query_str)
+            .bind(prefix.to_lowercase())
+            .fetch_all(&mut self.pool.acquire().await?)
+            .await?;
+
+        let node_ids = rows
+            .into_iter()
+            .flat_map(|row| Uuid::try_parse(row.get(0)))
+            .collect();
+
+        Ok(node_ids)
+    }
+
+    pub async fn get_nodes_by_title(&self, title: &str) -> anyhow::Result<Vec<NodeID>> {
+        let query_str = std::include_str!("sql/get_nodes_by_title.sql");
+        let rows = sqlx::query(query_str)
+            .bind(title)
+            .fetch_all(&mut self.pool.acquire().await?)
             .await?;
+
+        let node_ids = rows
+            .into_iter()
+            .flat_map(|row| Uuid::try_parse(row.get(0)))
+            .collect();
+
+        Ok(node_ids)
+    }
+
+    /// Whether `from` contains `to`.
+    pub async fn has_edge(&self, from: NodeID, to: NodeID) -> anyhow::Result<bool> {
+        let count = sqlx::query(
+            "SELECT COUNT(*) FROM edges WHERE from_uuid = ? AND to_uuid = ? AND kind = ?",
+        )
+        .bind(from.to_string())
+        .bind(to.to_string())
+        .bind(EdgeKind::Contains.name())
+        .fetch_one(&mut self.pool.acquire().await?)
+        .await?;
         let count: i64 = count.get(0);
         Ok(count > 0)
     }
 
     pub async fn get_children(&self, id: NodeID) -> anyhow::Result<Vec<Uuid>> {
+        self.get_targets(id, EdgeKind::Contains).await
+    }
+
+    pub async fn get_parents(&self, id: NodeID) -> anyhow::Result<Vec<NodeID>> {
+        self.get_sources(id, EdgeKind::Contains).await
+    }
+
+    /// The nodes which have to be done before `id` can be started, done or not.
+    pub async fn get_blockers(&self, id: NodeID) -> anyhow::Result<Vec<NodeID>> {
+        self.get_sources(id, EdgeKind::Blocks).await
+    }
+
+    /// The blockers of `id` which haven't been completed or trashed yet.
+    pub async fn get_open_blockers(&self, id: NodeID) -> anyhow::Result<Vec<Node>> {
+        let mut blockers = Vec::new();
+        for blocker in self.get_blockers(id).await?.into_iter() {
+            let blocker = self.get_node(blocker).await?;
+            if blocker.is_open() {
+                blockers.push(blocker);
+            }
+        }
+        Ok(blockers)
+    }
+
+    async fn get_targets(&self, id: NodeID, kind: EdgeKind) -> anyhow::Result<Vec<NodeID>> {
         let query_str = std::include_str!("sql/get_children.sql");
-        let children = sqlx::query(query_str)
+        let targets = sqlx::query(query_str)
+            .bind(id.to_string())
+            .bind(kind.name())
+            .fetch_all(&mut self.pool.acquire().await?)
+            .await?;
+
+        let targets = targets
+            .into_iter()
+            .flat_map(|row| Uuid::try_parse(row.get(0)))
+            .collect();
+
+        Ok(targets)
+    }
+
+    async fn get_sources(&self, id: NodeID, kind: EdgeKind) -> anyhow::Result<Vec<NodeID>> {
+        let query_str = std::include_str!("sql/get_parents.sql");
+        let sources = sqlx::query(query_str)
             .bind(id.to_string())
+            .bind(kind.name())
             .fetch_all(&mut self.pool.acquire().await?)
             .await?;
 
-        let children = children
+        let sources = sources
             .into_iter()
             .flat_map(|row| Uuid::try_parse(row.get(0)))
             .collect();
 
-        Ok(children)
+        Ok(sources)
     }
 
+    /// Returns the ancestors of `id`, ordered from a root down to its direct parent.
+    /// Nodes can have several parents, so this follows the first parent at each step.
+    pub async fn get_path(&self, id: NodeID) -> anyhow::Result<Vec<Node>> {
+        let mut seen = BTreeSet::new();
+        seen.insert(id);
+
+        let mut path = Vec::new();
+        let mut current = id;
+        while let Some(parent) = self.get_parents(current).await?.into_iter().next() {
+            if !seen.insert(parent) {
+                break;
+            }
+            path.push(self.get_node(parent).await?);
+            current = parent;
+        }
+        path.reverse();
+
+        Ok(path)
+    }
+
+    pub async fn get_dated_nodes(&self) -> anyhow::Result<Vec<NodeID>> {
+        let query_str = std::include_str!("sql/get_dated_nodes.sql");
+        let rows = sqlx::query(query_str)
+            .fetch_all(&mut self.pool.acquire().await?)
+            .await?;
+
+        let node_ids = rows
+            .into_iter()
+            .flat_map(|row| Uuid::try_parse(row.get(0)))
+            .collect();
+
+        Ok(node_ids)
+    }
+
+    /// Nodes without parents, leaving out the inbox:
+    /// it's somewhere for things to land, not an area of focus.
     pub async fn get_roots(&self) -> anyhow::Result<Vec<NodeID>> {
         let query_str = std::include_str!("sql/get_roots.sql");
         let roots = sqlx::query(query_str)
@@ -167,6 +477,111 @@ impl Database {
         Ok(roots)
     }
 
+    /// Finds the actionable leaves beneath `roots`:
+    /// open nodes which have no open children of their own.
+    /// Completed, trashed and blocked nodes are skipped along with everything beneath them,
+    /// and nodes reachable along several paths are only reported once.
+    pub async fn next_actions(&self, roots: &[NodeID]) -> anyhow::Result<Vec<NextAction>> {
+        let mut seen = BTreeSet::new();
+        let mut actions = Vec::new();
+        for root in roots.iter() {
+            let mut stack = vec![(*root, Vec::new())];
+            while let Some((id, path)) = stack.pop() {
+                if !seen.insert(id) {
+                    continue;
+                }
+                let node = self.get_node(id).await?;
+                if !node.is_open() || !self.get_open_blockers(id).await?.is_empty() {
+                    continue;
+                }
+
+                let mut open_children = Vec::new();
+                for child in self.get_children(id).await?.into_iter() {
+                    if self.get_node(child).await?.is_open() {
+                        open_children.push(child);
+                    }
+                }
+                if open_children.is_empty() {
+                    actions.push(NextAction { node, path });
+                    continue;
+                }
+
+                let mut child_path: Vec<Node> = path.clone();
+                child_path.push(node);
+                for child in open_children.into_iter() {
+                    if !seen.contains(&child) {
+                        stack.push((child, child_path.clone()));
+                    }
+                }
+            }
+        }
+        Ok(actions)
+    }
+
+    /// What `id` is, going by how many parents and children it has.
+    pub async fn kind(&self, id: NodeID) -> anyhow::Result<Kind> {
+        Ok(Kind::of(
+            self.get_parents(id).await?.len(),
+            self.get_children(id).await?.len(),
+        ))
+    }
+
+    /// How far along everything beneath `id` is.
+    /// Trashed nodes don't count, and neither does anything only reachable through them.
+    pub async fn stats(&self, id: NodeID) -> anyhow::Result<Stats> {
+        let node = self.get_node(id).await?;
+        let has_children = !self.get_children(id).await?.is_empty();
+        let open_leaves = self
+            .next_actions(&[id])
+            .await?
+            .iter()
+            .filter(|action| action.node.id != id)
+            .count();
+
+        let mut stats = Stats {
+            open_leaves,
+            completed: 0,
+            total: 0,
+            next_due: None,
+            stalled: false,
+        };
+        let is_open = node.is_open();
+        // Blocked nodes aren't counted as open leaves, but they're still something left to do.
+        let mut open_descendants = 0;
+        let mut seen = BTreeSet::new();
+        // Along with whether anything above it has been completed.
+        let mut stack = vec![(node, false)];
+        while let Some((node, under_completed)) = stack.pop() {
+            if !seen.insert(node.id) || node.trashed {
+                continue;
+            }
+            if node.id != id {
+                stats.total += 1;
+                if node.completed.is_some() {
+                    stats.completed += 1;
+                }
+            }
+            if node.is_open() && !under_completed {
+                if node.id != id {
+                    open_descendants += 1;
+                }
+                stats.next_due = match (stats.next_due, node.due) {
+                    (Some(next_due), Some(due)) => Some(next_due.min(due)),
+                    (next_due, due) => next_due.or(due),
+                };
+            }
+
+            let under_completed = under_completed || node.completed.is_some();
+            for child in self.get_children(node.id).await?.into_iter() {
+                if !seen.contains(&child) {
+                    stack.push((self.get_node(child).await?, under_completed));
+                }
+            }
+        }
+        stats.stalled = is_open && has_children && open_descendants == 0;
+        Ok(stats)
+    }
+
     pub async fn dfs(&self, root: NodeID) -> anyhow::Result<DFSIter<'_>> {
         self.exists_check(&root).await?;
 
@@ -177,18 +592,297 @@ impl Database {
         })
     }
 
+    /// A number which changes whenever another process writes to the database,
+    /// so that long-running views like the UI can tell when to reload.
+    pub async fn data_version(&self) -> anyhow::Result<i64> {
+        let row = sqlx::query("PRAGMA data_version")
+            .fetch_one(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(row.get(0))
+    }
+
+    /// The nodes which are expanded in the UI's tree view.
+    pub async fn get_expanded(&self) -> anyhow::Result<BTreeSet<NodeID>> {
+        let expanded = sqlx::query("SELECT uuid FROM expanded")
+            .fetch_all(&mut self.pool.acquire().await?)
+            .await?;
+
+        let expanded = expanded
+            .into_iter()
+            .flat_map(|row| Uuid::try_parse(row.get(0)))
+            .collect();
+
+        Ok(expanded)
+    }
+
+    pub async fn set_expanded(&self, id: NodeID, expanded: bool) -> anyhow::Result<()> {
+        let query_str = if expanded {
+            "INSERT OR IGNORE INTO expanded (uuid) VALUES (?)"
+        } else {
+            "DELETE FROM expanded WHERE uuid = ?"
+        };
+        sqlx::query(query_str)
+            .bind(id.to_string())
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    /// The node which `tasq capture` adds to, if there is one yet.
+    pub async fn get_inbox(&self) -> anyhow::Result<Option<NodeID>> {
+        let row = sqlx::query("SELECT inbox.uuid FROM inbox JOIN nodes ON nodes.uuid = inbox.uuid")
+            .fetch_optional(&mut self.pool.acquire().await?)
+            .await?;
+        row.map(|row| Ok(Uuid::try_parse(row.get(0))?)).transpose()
+    }
+
+    /// The node which `tasq capture` adds to.
+    /// It's made on `tasq init`, or on first use for databases from before it existed.
+    pub async fn inbox(&self) -> anyhow::Result<NodeID> {
+        if let Some(inbox) = self.get_inbox().await? {
+            return Ok(inbox);
+        }
+
+        let mut inbox = Node::new();
+        inbox.title = "Inbox".to_string();
+        // Both or neither, so that a failure doesn't leave a stray "Inbox" among the roots.
+        let mut transaction = self.pool.begin().await?;
+        insert_query(&inbox).execute(&mut transaction).await?;
+        sqlx::query("INSERT OR REPLACE INTO inbox (id, uuid) VALUES (0, ?)")
+            .bind(inbox.id.to_string())
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(inbox.id)
+    }
+
+    /// The inbox's children which still need processing:
+    /// those which are open and haven't been given a time to do them yet.
+    pub async fn get_inbox_items(&self) -> anyhow::Result<Vec<Node>> {
+        let Some(inbox) = self.get_inbox().await? else { return Ok(Vec::new()) };
+        let mut items = Vec::new();
+        for id in self.get_children(inbox).await?.into_iter() {
+            let node = self.get_node(id).await?;
+            if node.is_open() && node.scheduled.is_none() {
+                items.push(node);
+            }
+        }
+        Ok(items)
+    }
+
+    /// When each node was last looked at in a weekly review.
+    pub async fn get_reviewed(&self) -> anyhow::Result<BTreeMap<NodeID, DateTime<Utc>>> {
+        let rows = sqlx::query("SELECT uuid, reviewed FROM reviewed")
+            .fetch_all(&mut self.pool.acquire().await?)
+            .await?;
+
+        let mut reviewed = BTreeMap::new();
+        for row in rows.into_iter() {
+            let Ok(id) = Uuid::try_parse(row.get(0)) else { continue };
+            if let Some(at) = date_time_from_timestamp(row.get(1))? {
+                reviewed.insert(id, at);
+            }
+        }
+        Ok(reviewed)
+    }
+
+    pub async fn set_reviewed(&self, id: NodeID, at: DateTime<Utc>) -> anyhow::Result<()> {
+        self.exists_check(&id).await?;
+        sqlx::query("INSERT OR REPLACE INTO reviewed (uuid, reviewed) VALUES (?, ?)")
+            .bind(id.to_string())
+            .bind(at.timestamp_millis())
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(())
+    }
+
+    /// Starts timing `id`, stopping whatever timer was already running.
+    /// Returns the entry that was stopped, if there was one.
+    pub async fn start_timer(
+        &self,
+        id: NodeID,
+        at: DateTime<Utc>,
+    ) -> anyhow::Result<Option<TimeEntry>> {
+        self.exists_check(&id).await?;
+        let stopped = self.get_running_timer().await?.map(|entry| TimeEntry {
+            stopped: Some(at),
+            ..entry
+        });
+
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("UPDATE time_entries SET stopped = ? WHERE stopped IS NULL")
+            .bind(at.timestamp_millis())
+            .execute(&mut transaction)
+            .await?;
+        sqlx::query("INSERT INTO time_entries (uuid, started) VALUES (?, ?)")
+            .bind(id.to_string())
+            .bind(at.timestamp_millis())
+            .execute(&mut transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(stopped)
+    }
+
+    /// Stops the running timer, returning its finished entry if there was one.
+    pub async fn stop_timer(&self, at: DateTime<Utc>) -> anyhow::Result<Option<TimeEntry>> {
+        let Some(entry) = self.get_running_timer().await? else { return Ok(None) };
+        sqlx::query("UPDATE time_entries SET stopped = ? WHERE stopped IS NULL")
+            .bind(at.timestamp_millis())
+            .execute(&mut self.pool.acquire().await?)
+            .await?;
+        Ok(Some(TimeEntry {
+            stopped: Some(at),
+            ..entry
+        }))
+    }
+
+    pub async fn get_running_timer(&self) -> anyhow::Result<Option<TimeEntry>> {
+        let row = sqlx::query(
+            "SELECT time_entries.uuid, started, stopped FROM time_entries \
+             JOIN nodes ON nodes.uuid = time_entries.uuid \
+             WHERE stopped IS NULL",
+        )
+        .fetch_optional(&mut self.pool.acquire().await?)
+        .await?;
+        row.map(TimeEntry::try_from).transpose()
+    }
+
+    /// Entries which overlap the time between `since` and `until`.
+    /// They aren't clipped to it; see [`TimeEntry::duration_between`].
+    pub async fn get_time_entries(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<TimeEntry>> {
+        let rows = sqlx::query(
+            "SELECT time_entries.uuid, started, stopped FROM time_entries \
+             JOIN nodes ON nodes.uuid = time_entries.uuid \
+             WHERE started < ? AND (stopped IS NULL OR stopped > ?) \
+             ORDER BY started",
+        )
+        .bind(until.timestamp_millis())
+        .bind(since.map_or(i64::MIN, |since| since.timestamp_millis()))
+        .fetch_all(&mut self.pool.acquire().await?)
+        .await?;
+        rows.into_iter().map(TimeEntry::try_from).collect()
+    }
+
     async fn exists_check(&self, id: &NodeID) -> anyhow::Result<()> {
         let nodes = sqlx::query("SELECT * FROM nodes WHERE uuid = ?")
             .bind(id.to_string())
             .fetch_all(&mut self.pool.acquire().await?)
             .await?;
-        if nodes.len() == 0 {
+        if nodes.is_empty() {
             return Err(anyhow!("Missing node {}", id));
         }
         Ok(())
     }
 }
 
+/// Does the work of `Database::would_create_cycle` on `connection`,
+/// so that it can see edges made earlier in a transaction.
+async fn creates_cycle(
+    connection: &mut SqliteConnection,
+    from: NodeID,
+    to: NodeID,
+) -> anyhow::Result<bool> {
+    let mut seen = BTreeSet::new();
+    let mut stack = vec![to];
+    while let Some(id) = stack.pop() {
+        if id == from {
+            return Ok(true);
+        }
+        if !seen.insert(id) {
+            continue;
+        }
+        let rows = sqlx::query("SELECT to_uuid FROM edges WHERE from_uuid = ?")
+            .bind(id.to_string())
+            .fetch_all(&mut *connection)
+            .await?;
+        stack.extend(rows.into_iter().flat_map(|row| Uuid::try_parse(row.get(0))));
+    }
+    Ok(false)
+}
+
+fn insert_query(node: &Node) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+    sqlx::query(std::include_str!("sql/insert_node.sql"))
+        .bind(node.id.to_string())
+        .bind(&node.title)
+        .bind(&node.description)
+        .bind(node.scheduled.map(|dt| dt.timestamp_millis()))
+        .bind(node.due.map(|dt| dt.timestamp_millis()))
+        .bind(node.completed.map(|dt| dt.timestamp_millis()))
+        .bind(node.trashed)
+        .bind(node.estimate.map(|estimate| estimate.num_minutes()))
+}
+
+fn update_query(node: &Node) -> Query<'_, Sqlite, SqliteArguments<'_>> {
+    sqlx::query(std::include_str!("sql/update_node.sql"))
+        .bind(&node.title)
+        .bind(&node.description)
+        .bind(node.scheduled.map(|dt| dt.timestamp_millis()))
+        .bind(node.due.map(|dt| dt.timestamp_millis()))
+        .bind(node.completed.map(|dt| dt.timestamp_millis()))
+        .bind(node.trashed)
+        .bind(node.estimate.map(|estimate| estimate.num_minutes()))
+        .bind(node.id.to_string())
+}
+
+fn connect_query<'q>(
+    from: NodeID,
+    to: NodeID,
+    kind: EdgeKind,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query(std::include_str!("sql/connect_nodes.sql"))
+        .bind(from.to_string())
+        .bind(to.to_string())
+        .bind(kind.name())
+}
+
+fn disconnect_query<'q>(
+    from: NodeID,
+    to: NodeID,
+    kind: EdgeKind,
+) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+    sqlx::query("DELETE FROM edges WHERE from_uuid = ? AND to_uuid = ? AND kind = ?")
+        .bind(from.to_string())
+        .bind(to.to_string())
+        .bind(kind.name())
+}
+
+/// One of a batch of changes made together with [`Database::apply`].
+pub enum Change {
+    Add(Node),
+    Update(Node),
+    /// Adds an edge from a parent to a child.
+    Connect(NodeID, NodeID),
+    /// Removes the edge from a parent to a child.
+    Disconnect(NodeID, NodeID),
+    /// Adds an edge from a blocker to the node it blocks.
+    Block(NodeID, NodeID),
+    /// Removes the edge from a blocker to the node it blocks.
+    Unblock(NodeID, NodeID),
+}
+
+/// What an edge from one node to another means.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// The edge's target is part of its source, as a task is part of a project.
+    Contains,
+    /// The edge's target can't be started until its source is done.
+    Blocks,
+}
+
+impl EdgeKind {
+    /// How the kind is stored in the database.
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeKind::Contains => "contains",
+            EdgeKind::Blocks => "blocks",
+        }
+    }
+}
+
 pub struct DFSIter<'a> {
     database: &'a Database,
     seen: BTreeSet<NodeID>,
@@ -198,9 +892,9 @@ pub struct DFSIter<'a> {
 impl<'a> DFSIter<'a> {
     pub async fn next(&mut self) -> anyhow::Result<Option<(NodeID, usize)>> {
         let Some((next, depth)) = self.stack.pop() else {
-	    return Ok(None);
-	};
-        self.seen.insert(next.clone());
+            return Ok(None);
+        };
+        self.seen.insert(next);
         for child in self.database.get_children(next).await? {
             if self.seen.contains(&child) {
                 continue;
@@ -213,6 +907,107 @@ impl<'a> DFSIter<'a> {
 
 pub type NodeID = Uuid;
 
+pub struct NextAction {
+    pub node: Node,
+    /// Ancestors of `node`, from the root it was found under down to its parent.
+    pub path: Vec<Node>,
+}
+
+/// What a node is, going by where it sits in the graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    /// A root with children: an area of focus.
+    Area,
+    /// Something with both parents and children.
+    Project,
+    /// A leaf beneath something else.
+    Task,
+    /// Not connected to anything at all.
+    Orphan,
+}
+
+impl Kind {
+    pub fn of(parent_count: usize, child_count: usize) -> Self {
+        match (parent_count, child_count) {
+            (0, 0) => Kind::Orphan,
+            (0, _) => Kind::Area,
+            (_, 0) => Kind::Task,
+            (_, _) => Kind::Project,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        use Kind::*;
+        match self {
+            Area => "area",
+            Project => "project",
+            Task => "task",
+            Orphan => "orphan",
+        }
+    }
+}
+
+/// A summary of the nodes beneath an area or project.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Stats {
+    /// Open nodes beneath this one with no open children, as `tasq next` would list them.
+    pub open_leaves: usize,
+    pub completed: usize,
+    /// How many nodes are beneath this one, not counting trashed ones.
+    pub total: usize,
+    /// The earliest due date of this node and anything open beneath it.
+    #[serde(with = "ts_seconds_option")]
+    pub next_due: Option<DateTime<Utc>>,
+    /// Whether this is open and has children, but there's nothing beneath it left to do.
+    pub stalled: bool,
+}
+
+impl Stats {
+    /// The percentage of nodes beneath this one which have been completed,
+    /// if there are any.
+    pub fn completion(&self) -> Option<usize> {
+        if self.total == 0 {
+            return None;
+        }
+        Some(100 * self.completed / self.total)
+    }
+}
+
+/// A stretch of time spent on a node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeEntry {
+    pub node: NodeID,
+    pub started: DateTime<Utc>,
+    /// When the timer was stopped, or `None` while it's still running.
+    pub stopped: Option<DateTime<Utc>>,
+}
+
+impl TimeEntry {
+    /// How much of this entry falls between `since` and `until`,
+    /// counting a running entry as if it stopped at `until`.
+    pub fn duration_between(&self, since: Option<DateTime<Utc>>, until: DateTime<Utc>) -> Duration {
+        let started = since.map_or(self.started, |since| self.started.max(since));
+        let stopped = self.stopped.map_or(until, |stopped| stopped.min(until));
+        (stopped - started).max(Duration::zero())
+    }
+}
+
+impl TryFrom<SqliteRow> for TimeEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(value: SqliteRow) -> Result<Self, Self::Error> {
+        let Some(started) = date_time_from_timestamp(value.get("started"))? else {
+            return Err(anyhow!("Time entry is missing its start"));
+        };
+        Ok(Self {
+            node: Uuid::try_parse(value.get("uuid"))?,
+            started,
+            stopped: date_time_from_timestamp(value.get("stopped"))?,
+        })
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Node {
     pub id: NodeID,
@@ -225,6 +1020,9 @@ pub struct Node {
     #[serde(with = "ts_seconds_option")]
     pub completed: Option<DateTime<Utc>>,
     pub trashed: bool,
+    /// How long the node should take to do, written as a number of minutes.
+    #[serde(default, with = "minutes_option")]
+    pub estimate: Option<Duration>,
 }
 
 impl Node {
@@ -237,12 +1035,56 @@ impl Node {
             due: None,
             completed: None,
             trashed: false,
+            estimate: None,
         }
     }
 
+    pub fn is_open(&self) -> bool {
+        self.completed.is_none() && !self.trashed
+    }
+
     pub fn short_repr(&self) -> String {
         format!("{} ({})", self.title, self.id)
     }
+
+    /// Combines two sets of edits made to `base`.
+    /// Fields which `ours` changed win, and everything else comes from `theirs`.
+    pub fn merge(base: &Node, ours: &Node, theirs: &Node) -> Node {
+        fn pick<T: PartialEq + Clone>(base: &T, ours: &T, theirs: &T) -> T {
+            if ours != base {
+                ours.clone()
+            } else {
+                theirs.clone()
+            }
+        }
+
+        Node {
+            id: theirs.id,
+            title: pick(&base.title, &ours.title, &theirs.title),
+            description: pick(&base.description, &ours.description, &theirs.description),
+            scheduled: pick(&base.scheduled, &ours.scheduled, &theirs.scheduled),
+            due: pick(&base.due, &ours.due, &theirs.due),
+            completed: pick(&base.completed, &ours.completed, &theirs.completed),
+            trashed: pick(&base.trashed, &ours.trashed, &theirs.trashed),
+            estimate: pick(&base.estimate, &ours.estimate, &theirs.estimate),
+        }
+    }
+}
+
+// Dates are stored as milliseconds,
+// so a node only matches what's in the database to that precision.
+impl PartialEq for Node {
+    fn eq(&self, other: &Self) -> bool {
+        let millis = |date: Option<DateTime<Utc>>| date.map(|date| date.timestamp_millis());
+        self.id == other.id
+            && self.title == other.title
+            && self.description == other.description
+            && millis(self.scheduled) == millis(other.scheduled)
+            && millis(self.due) == millis(other.due)
+            && millis(self.completed) == millis(other.completed)
+            && self.trashed == other.trashed
+            && self.estimate == other.estimate
+    }
 }
 
 impl TryFrom<SqliteRow> for Node {
@@ -257,14 +1099,549 @@ impl TryFrom<SqliteRow> for Node {
             due: date_time_from_timestamp(value.get("due"))?,
             completed: date_time_from_timestamp(value.get("completed"))?,
             trashed: value.get("trashed"),
+            estimate: value
+                .get::<Option<i64>, _>("estimate")
+                .map(Duration::minutes),
         })
     }
 }
 
-fn date_time_from_timestamp(timestamp: Option<i64>) -> anyhow::Result<Option<DateTime<Utc>>> {
+// Timestamp columns are declared as REAL,
+// so SQLite hands back the millisecond timestamps we insert as floats.
+fn date_time_from_timestamp(timestamp: Option<f64>) -> anyhow::Result<Option<DateTime<Utc>>> {
     let Some(timestamp) = timestamp else { return Ok(None); };
-    match Utc.timestamp_millis_opt(timestamp) {
+    match Utc.timestamp_millis_opt(timestamp as i64) {
         LocalResult::Single(dt) => Ok(Some(dt)),
         x => Err(anyhow!("Couldn't parse DateTime from timestamp: {:?}", x)),
     }
 }
+
+/// Serializes an optional duration as a whole number of minutes,
+/// which is precise enough for estimates and easy to write by hand.
+pub mod minutes_option {
+    use chrono::Duration;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serialize;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        duration
+            .map(|duration| duration.num_minutes())
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        // Unsigned, so that negative estimates are turned away.
+        let minutes = Option::<u32>::deserialize(deserializer)?;
+        Ok(minutes.map(|minutes| Duration::minutes(minutes.into())))
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    async fn test_database() -> anyhow::Result<(tempfile::TempDir, Database)> {
+        let temp_dir = tempfile::tempdir()?;
+        let database = Database::create(temp_dir.path().join("graph.sqlite3")).await?;
+        Ok((temp_dir, database))
+    }
+
+    async fn add_node(database: &Database, title: &str) -> anyhow::Result<NodeID> {
+        let mut node = Node::new();
+        node.title = title.to_string();
+        database.add(&node).await?;
+        Ok(node.id)
+    }
+
+    #[test]
+    fn test_merge__keeps_both_sides_changes() {
+        let base = Node::new();
+        let mut ours = base.clone();
+        ours.title = "ours".to_string();
+        let mut theirs = base.clone();
+        theirs.description = "theirs".to_string();
+        theirs.trashed = true;
+
+        let merged = Node::merge(&base, &ours, &theirs);
+        assert_eq!(merged.title, "ours");
+        assert_eq!(merged.description, "theirs");
+        assert!(merged.trashed);
+    }
+
+    #[test]
+    fn test_data_version__changes_on_outside_writes() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (temp_dir, database) = test_database().await?;
+            let before = database.data_version().await?;
+
+            // Our own writes don't count...
+            add_node(&database, "ours").await?;
+            assert_eq!(database.data_version().await?, before);
+
+            // ...but anyone else's do.
+            let other = Database::open(temp_dir.path().join("graph.sqlite3")).await?;
+            add_node(&other, "theirs").await?;
+            assert_ne!(database.data_version().await?, before);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_set_expanded__round_trips() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let node = add_node(&database, "node").await?;
+
+            database.set_expanded(node, true).await?;
+            // Expanding twice shouldn't trip over the primary key.
+            database.set_expanded(node, true).await?;
+            assert_eq!(database.get_expanded().await?, BTreeSet::from([node]));
+
+            database.set_expanded(node, false).await?;
+            assert!(database.get_expanded().await?.is_empty());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_set_reviewed__replaces_older_review() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let node = add_node(&database, "project").await?;
+            let earlier = Utc.timestamp_millis_opt(1_000_000).unwrap();
+            let later = Utc.timestamp_millis_opt(2_000_000).unwrap();
+
+            database.set_reviewed(node, earlier).await?;
+            database.set_reviewed(node, later).await?;
+            assert_eq!(database.get_reviewed().await?, BTreeMap::from([(node, later)]));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_inbox__made_once() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let inbox = database.inbox().await?;
+            assert_eq!(database.inbox().await?, inbox);
+            assert_eq!(database.get_roots().await?, Vec::<NodeID>::new());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_get_inbox_items__without_inbox() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            assert!(database.get_inbox_items().await?.is_empty());
+            assert_eq!(database.get_inbox().await?, None);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_get_inbox_items__skips_processed() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let inbox = database.inbox().await?;
+            let mut items = Vec::new();
+            for title in ["new", "done", "scheduled"] {
+                let item = add_node(&database, title).await?;
+                database.connect(inbox, item).await?;
+                items.push(database.get_node(item).await?);
+            }
+            items[1].completed = Some(Utc::now());
+            items[2].scheduled = Some(Utc::now());
+            database.update(&items[1]).await?;
+            database.update(&items[2]).await?;
+
+            let unprocessed = database.get_inbox_items().await?;
+            assert_eq!(unprocessed, vec![items[0].clone()]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_apply__makes_every_change() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let old_parent = add_node(&database, "old").await?;
+            let child = add_node(&database, "child").await?;
+            database.connect(old_parent, child).await?;
+
+            let mut new_parent = Node::new();
+            new_parent.title = "new".to_string();
+            let mut updated = database.get_node(child).await?;
+            updated.trashed = true;
+            database
+                .apply(&[
+                    Change::Add(new_parent.clone()),
+                    Change::Update(updated),
+                    Change::Disconnect(old_parent, child),
+                    Change::Connect(new_parent.id, child),
+                ])
+                .await?;
+
+            assert!(database.get_node(child).await?.trashed);
+            assert_eq!(database.get_parents(child).await?, vec![new_parent.id]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_apply__all_or_nothing() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let parent = add_node(&database, "parent").await?;
+            let child = add_node(&database, "child").await?;
+
+            let mut updated = database.get_node(child).await?;
+            updated.title = "renamed".to_string();
+            // The second edge trips over the primary key, which only the database notices.
+            let result = database
+                .apply(&[
+                    Change::Update(updated),
+                    Change::Connect(parent, child),
+                    Change::Connect(parent, child),
+                ])
+                .await;
+
+            assert!(result.is_err());
+            assert_eq!(database.get_node(child).await?.title, "child");
+            assert!(!database.has_edge(parent, child).await?);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_apply__rejects_cycles() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let parent = add_node(&database, "parent").await?;
+            let child = add_node(&database, "child").await?;
+            database.connect(parent, child).await?;
+
+            let result = database.apply(&[Change::Connect(child, parent)]).await;
+            assert!(result.is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_apply__rejects_cycles_within_batch() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let a = add_node(&database, "a").await?;
+            let mut node = Node::new();
+            node.title = "new".to_string();
+            let n = node.id;
+
+            let result = database
+                .apply(&[Change::Add(node), Change::Connect(a, n), Change::Connect(n, a)])
+                .await;
+            assert!(result.is_err());
+            // Nothing from the batch was kept.
+            assert!(database.get_node(n).await.is_err());
+            assert!(database.get_children(a).await?.is_empty());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_next_actions__skips_closed_nodes() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let project = add_node(&database, "project").await?;
+            let open = add_node(&database, "open").await?;
+            let done = add_node(&database, "done").await?;
+            database.connect(project, open).await?;
+            database.connect(project, done).await?;
+
+            let mut node = database.get_node(done).await?;
+            node.completed = Some(Utc::now());
+            database.update(&node).await?;
+
+            let actions = database.next_actions(&[project]).await?;
+            let ids: Vec<NodeID> = actions.iter().map(|action| action.node.id).collect();
+            assert_eq!(ids, vec![open]);
+            assert_eq!(actions[0].path[0].id, project);
+
+            // Once every child is done, the project itself is what's next.
+            let mut node = database.get_node(open).await?;
+            node.trashed = true;
+            database.update(&node).await?;
+            let actions = database.next_actions(&[project]).await?;
+            let ids: Vec<NodeID> = actions.iter().map(|action| action.node.id).collect();
+            assert_eq!(ids, vec![project]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_kind__of() {
+        assert_eq!(Kind::of(0, 0), Kind::Orphan);
+        assert_eq!(Kind::of(0, 2), Kind::Area);
+        assert_eq!(Kind::of(1, 2), Kind::Project);
+        assert_eq!(Kind::of(2, 0), Kind::Task);
+    }
+
+    #[test]
+    fn test_stats__project() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let project = add_node(&database, "project").await?;
+            let open = add_node(&database, "open").await?;
+            let done = add_node(&database, "done").await?;
+            let trashed = add_node(&database, "trashed").await?;
+            for child in [open, done, trashed] {
+                database.connect(project, child).await?;
+            }
+
+            let due = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+            let mut node = database.get_node(open).await?;
+            node.due = Some(due);
+            database.update(&node).await?;
+            let mut node = database.get_node(done).await?;
+            node.completed = Some(Utc::now());
+            node.due = Some(due - chrono::Duration::days(1));
+            database.update(&node).await?;
+            let mut node = database.get_node(trashed).await?;
+            node.trashed = true;
+            database.update(&node).await?;
+
+            let stats = database.stats(project).await?;
+            assert_eq!(stats.open_leaves, 1);
+            assert_eq!((stats.completed, stats.total), (1, 2));
+            assert_eq!(stats.completion(), Some(50));
+            assert_eq!(stats.next_due, Some(due));
+            assert!(!stats.stalled);
+
+            let mut node = database.get_node(open).await?;
+            node.completed = Some(Utc::now());
+            database.update(&node).await?;
+            let stats = database.stats(project).await?;
+            assert_eq!(stats.open_leaves, 0);
+            assert_eq!(stats.completion(), Some(100));
+            assert_eq!(stats.next_due, None);
+            assert!(stats.stalled);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_would_create_cycle__self_edge() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let a = add_node(&database, "a").await?;
+            assert!(database.would_create_cycle(a, a).await?);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_would_create_cycle__through_descendants() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let a = add_node(&database, "a").await?;
+            let b = add_node(&database, "b").await?;
+            let c = add_node(&database, "c").await?;
+            database.connect(a, b).await?;
+            database.connect(b, c).await?;
+
+            assert!(database.would_create_cycle(c, a).await?);
+            assert!(!database.would_create_cycle(a, c).await?);
+            assert!(database.connect(c, a).await.is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_would_create_cycle__across_edge_kinds() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let a = add_node(&database, "a").await?;
+            let b = add_node(&database, "b").await?;
+            let c = add_node(&database, "c").await?;
+            database.connect(a, b).await?;
+            database.block(b, c).await?;
+
+            assert!(database.would_create_cycle(c, a).await?);
+            assert!(database.block(c, a).await.is_err());
+            assert!(database.connect(c, a).await.is_err());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_block__alongside_containment() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let a = add_node(&database, "a").await?;
+            let b = add_node(&database, "b").await?;
+            database.connect(a, b).await?;
+            database.block(a, b).await?;
+
+            assert_eq!(database.get_children(a).await?, vec![b]);
+            assert_eq!(database.get_blockers(b).await?, vec![a]);
+
+            database.apply(&[Change::Unblock(a, b)]).await?;
+            assert_eq!(database.get_children(a).await?, vec![b]);
+            assert_eq!(database.get_blockers(b).await?, Vec::<NodeID>::new());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_block__not_a_child() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let a = add_node(&database, "a").await?;
+            let b = add_node(&database, "b").await?;
+            database.block(a, b).await?;
+
+            assert_eq!(database.get_children(a).await?, vec![]);
+            assert_eq!(database.get_blockers(b).await?, vec![a]);
+            assert!(!database.has_edge(a, b).await?);
+            let mut roots = database.get_roots().await?;
+            roots.sort();
+            let mut expected = vec![a, b];
+            expected.sort();
+            assert_eq!(roots, expected);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_next_actions__skips_blocked_nodes() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let project = add_node(&database, "project").await?;
+            let first = add_node(&database, "first").await?;
+            let second = add_node(&database, "second").await?;
+            database.connect(project, first).await?;
+            database.connect(project, second).await?;
+            database.block(first, second).await?;
+
+            let actions = database.next_actions(&[project]).await?;
+            let ids: Vec<NodeID> = actions.iter().map(|action| action.node.id).collect();
+            assert_eq!(ids, vec![first]);
+
+            let mut node = database.get_node(first).await?;
+            node.completed = Some(Utc::now());
+            database.update(&node).await?;
+            let actions = database.next_actions(&[project]).await?;
+            let ids: Vec<NodeID> = actions.iter().map(|action| action.node.id).collect();
+            assert_eq!(ids, vec![second]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_add_column__upgrades_old_databases() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let temp_dir = tempfile::tempdir()?;
+            let path = temp_dir.path().join("graph.sqlite3");
+            let database = Database::create(&path).await?;
+            let a = add_node(&database, "a").await?;
+            let b = add_node(&database, "b").await?;
+            // Put the edges table back the way it was before edges had kinds.
+            let mut conn = database.pool.acquire().await?;
+            sqlx::query("DROP TABLE edges").execute(&mut conn).await?;
+            sqlx::query(
+                "CREATE TABLE edges (from_uuid TEXT, to_uuid TEXT, PRIMARY KEY(from_uuid, to_uuid))",
+            )
+            .execute(&mut conn)
+            .await?;
+            sqlx::query("INSERT INTO edges (from_uuid, to_uuid) VALUES (?, ?)")
+                .bind(a.to_string())
+                .bind(b.to_string())
+                .execute(&mut conn)
+                .await?;
+            drop(conn);
+            drop(database);
+
+            let database = Database::open(&path).await?;
+            assert_eq!(database.get_children(a).await?, vec![b]);
+            database.block(a, b).await?;
+            assert_eq!(database.get_blockers(b).await?, vec![a]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_start_timer__stops_running_timer() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let (_temp_dir, database) = test_database().await?;
+            let a = add_node(&database, "a").await?;
+            let b = add_node(&database, "b").await?;
+            let start = Utc.with_ymd_and_hms(2026, 10, 19, 9, 0, 0).unwrap();
+
+            assert_eq!(database.start_timer(a, start).await?, None);
+            let stopped = database.start_timer(b, start + Duration::hours(1)).await?;
+            assert_eq!(
+                stopped,
+                Some(TimeEntry {
+                    node: a,
+                    started: start,
+                    stopped: Some(start + Duration::hours(1)),
+                })
+            );
+            assert_eq!(
+                database.get_running_timer().await?.map(|entry| entry.node),
+                Some(b)
+            );
+
+            database.stop_timer(start + Duration::hours(2)).await?;
+            assert_eq!(database.get_running_timer().await?, None);
+            assert_eq!(database.stop_timer(start + Duration::hours(3)).await?, None);
+
+            let entries = database
+                .get_time_entries(
+                    Some(start + Duration::minutes(90)),
+                    start + Duration::hours(3),
+                )
+                .await?;
+            assert_eq!(
+                entries.iter().map(|entry| entry.node).collect::<Vec<_>>(),
+                vec![b]
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_time_entry__duration_between() {
+        let start = Utc.with_ymd_and_hms(2026, 10, 19, 9, 0, 0).unwrap();
+        let mut entry = TimeEntry {
+            node: Uuid::new_v4(),
+            started: start,
+            stopped: Some(start + Duration::hours(2)),
+        };
+        assert_eq!(
+            entry.duration_between(None, start + Duration::days(1)),
+            Duration::hours(2)
+        );
+        assert_eq!(
+            entry.duration_between(Some(start + Duration::hours(1)), start + Duration::days(1)),
+            Duration::hours(1)
+        );
+        assert_eq!(
+            entry.duration_between(Some(start + Duration::hours(3)), start + Duration::days(1)),
+            Duration::zero()
+        );
+
+        entry.stopped = None;
+        assert_eq!(
+            entry.duration_between(None, start + Duration::hours(5)),
+            Duration::hours(5)
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::fs::File;
+use std::process::Stdio;
+
+use structopt::StructOpt;
+
+use crate::config;
+use crate::db;
+use crate::node_ref::NodeRef;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    node: NodeRef,
+}
+
+pub async fn main(
+    args: Args,
+    database: db::Database,
+    config: &config::Config,
+    exact: bool,
+) -> anyhow::Result<()> {
+    let node = database
+        .get_node(args.node.resolve(&database, exact).await?)
+        .await?;
+    if let Some(node) = edit_node(&config.editor_command()?, &node)? {
+        database.update(&node).await?;
+    }
+    Ok(())
+}
+
+/// Opens `node` in `editor` as JSON and reads back whatever was saved.
+/// The editor takes over the terminal until it exits.
+/// Returns `None` if the editor fails, in which case the node should be left alone.
+pub fn edit_node(editor: &[String], node: &db::Node) -> anyhow::Result<Option<db::Node>> {
+    let temp_dir = tempfile::tempdir()?;
+    let mut filename = temp_dir.path().to_path_buf();
+    filename.push(format!("{}.json", node.id));
+
+    {
+        let file = File::create(&filename)?;
+        serde_json::to_writer_pretty(file, node)?;
+    };
+
+    let status = std::process::Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(&filename)
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .status()?;
+    if !status.success() {
+        return Ok(None);
+    }
+
+    let file = File::open(&filename)?;
+    Ok(Some(serde_json::from_reader(file)?))
+}
@@ -0,0 +1,36 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use structopt::StructOpt;
+
+use crate::db;
+use crate::ical;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    #[structopt(short = "f", long = "format", default_value = "ics")]
+    format: Format,
+}
+
+#[derive(Debug)]
+enum Format {
+    Ics,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ics" => Ok(Format::Ics),
+            _ => Err(anyhow!("Unknown export format {}", s)),
+        }
+    }
+}
+
+pub async fn main(args: Args, database: db::Database) -> anyhow::Result<()> {
+    match args.format {
+        Format::Ics => print!("{}", ical::render_calendar(&database).await?),
+    }
+    Ok(())
+}
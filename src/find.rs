@@ -3,6 +3,7 @@ use std::str;
 use structopt::StructOpt;
 
 use crate::db;
+use crate::output;
 
 const INSERT_COST: usize = 0;
 const DELETE_COST: usize = 1;
@@ -14,10 +15,20 @@ pub struct Args {
     text: String,
 }
 
-pub async fn main(args: Args, database: db::Database) -> anyhow::Result<()> {
+pub async fn main(
+    args: Args,
+    database: db::Database,
+    output: &mut output::Output,
+) -> anyhow::Result<()> {
     let candidates = find_candidates(&args.text, &database).await?;
-    for (candidate, _) in candidates.into_iter() {
-        println!("{} {}", candidate.title, candidate.id);
+    for (candidate, distance) in candidates.into_iter() {
+        let path = database.get_path(candidate.id).await?;
+        let record = output::Record::new(candidate)
+            .with_path(&path)
+            .with_score(distance);
+        output.emit(record, |record| {
+            format!("{} {}", record.node.title, record.node.id)
+        })?;
     }
     Ok(())
 }
@@ -54,30 +65,27 @@ fn levenshtein_distance(from: &str, to: &str) -> usize {
     let mut last_row = vec![0; to_chars.len() + 1];
     let mut this_row = vec![0; to_chars.len() + 1];
 
-    for i in 0..last_row.len() {
-        last_row[i] = i * INSERT_COST
+    for (i, cost) in last_row.iter_mut().enumerate() {
+        *cost = i * INSERT_COST
     }
 
-    for i in 0..from_chars.len() {
+    for (i, from_char) in from_chars.iter().enumerate() {
         this_row[0] = i + 1;
 
-        for j in 0..to_chars.len() {
+        for (j, to_char) in to_chars.iter().enumerate() {
             let deletion_cost = last_row[j + 1] + DELETE_COST;
             let insertion_cost = this_row[j] + INSERT_COST;
-	    let replacement_cost;
-	    if from_chars[i] == to_chars[j] {
-		replacement_cost = last_row[j];
-	    } else {
-		replacement_cost = last_row[j] + REPLACE_COST;
-	    }
-
-	    this_row[j + 1] = deletion_cost.min(insertion_cost).min(replacement_cost)
+            let replacement_cost = if from_char == to_char {
+                last_row[j]
+            } else {
+                last_row[j] + REPLACE_COST
+            };
+
+            this_row[j + 1] = deletion_cost.min(insertion_cost).min(replacement_cost)
         }
 
-	let tmp = last_row;
-	last_row = this_row;
-	this_row = tmp;
+        std::mem::swap(&mut last_row, &mut this_row);
     }
 
-    return last_row[to_chars.len()]
+    last_row[to_chars.len()]
 }
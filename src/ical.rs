@@ -0,0 +1,242 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_std::io::ReadExt;
+use async_std::io::WriteExt;
+use async_std::net::TcpListener;
+use async_std::net::TcpStream;
+use async_std::stream::StreamExt;
+use chrono::DateTime;
+use chrono::Utc;
+use structopt::StructOpt;
+
+use crate::db;
+
+// RFC 5545 limits content lines to 75 octets, not counting the CRLF.
+const MAX_LINE_OCTETS: usize = 75;
+
+// Clients are served one at a time, so one that never finishes its request
+// would otherwise hold up everyone else.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, StructOpt)]
+pub enum Args {
+    /// Serve the iCalendar feed over local HTTP so calendar apps can subscribe to it.
+    Serve(ServeArgs),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct ServeArgs {
+    #[structopt(short = "p", long = "port", default_value = "5545")]
+    port: u16,
+}
+
+pub async fn main(args: Args, database: db::Database) -> anyhow::Result<()> {
+    match args {
+        Args::Serve(args) => serve(args, database).await,
+    }
+}
+
+async fn serve(args: ServeArgs, database: db::Database) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", args.port)).await?;
+    eprintln!("Serving calendar on http://{}/", listener.local_addr()?);
+
+    let mut incoming = listener.incoming();
+    while let Some(stream) = incoming.next().await {
+        // A single misbehaving client shouldn't take the whole feed down.
+        if let Err(e) = respond(stream?, &database).await {
+            eprintln!("Failed to serve calendar: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+async fn respond(mut stream: TcpStream, database: &db::Database) -> anyhow::Result<()> {
+    let request = async_std::future::timeout(REQUEST_TIMEOUT, read_request(&mut stream))
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for a request"))??;
+    let request = String::from_utf8_lossy(&request);
+    let method = request.split_whitespace().next().unwrap_or("");
+    let response = match method {
+        "GET" | "HEAD" => {
+            let body = render_calendar(database).await?;
+            let mut response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/calendar; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                body.len(),
+            );
+            if method == "GET" {
+                response.push_str(&body);
+            }
+            response
+        }
+        _ => "HTTP/1.1 405 Method Not Allowed\r\nAllow: GET, HEAD\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+/// Reads the head of an HTTP request.
+/// We serve the same feed for every path,
+/// so we only need to read enough of the request to find its method.
+async fn read_request(stream: &mut TcpStream) -> anyhow::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") && request.len() < 8192 {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    Ok(request)
+}
+
+/// Renders every scheduled or due node as a VTODO inside a single VCALENDAR.
+pub async fn render_calendar(database: &db::Database) -> anyhow::Result<String> {
+    let now = Utc::now();
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//crockeo//tasq//EN".to_string(),
+        "CALSCALE:GREGORIAN".to_string(),
+    ];
+    for node_id in database.get_dated_nodes().await?.into_iter() {
+        let node = database.get_node(node_id).await?;
+        let path = database.get_path(node_id).await?;
+        lines.extend(render_todo(&node, &path, now));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut calendar = String::new();
+    for line in lines.iter() {
+        calendar.push_str(&fold_line(line));
+        calendar.push_str("\r\n");
+    }
+    Ok(calendar)
+}
+
+fn render_todo(node: &db::Node, path: &[db::Node], now: DateTime<Utc>) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VTODO".to_string(),
+        format!("UID:{}", node.id),
+        format!("DTSTAMP:{}", format_date_time(now)),
+        format!("SUMMARY:{}", escape_text(&node.title)),
+    ];
+    if !node.description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape_text(&node.description)));
+    }
+    if let Some(scheduled) = node.scheduled {
+        lines.push(format!("DTSTART:{}", format_date_time(scheduled)));
+    }
+    if let Some(due) = node.due {
+        lines.push(format!("DUE:{}", format_date_time(due)));
+    }
+    if let Some(completed) = node.completed {
+        lines.push(format!("COMPLETED:{}", format_date_time(completed)));
+        lines.push("STATUS:COMPLETED".to_string());
+    } else {
+        lines.push("STATUS:NEEDS-ACTION".to_string());
+    }
+    if !path.is_empty() {
+        let categories: Vec<String> = path.iter().map(|node| escape_text(&node.title)).collect();
+        lines.push(format!("CATEGORIES:{}", categories.join(",")));
+    }
+    lines.push("END:VTODO".to_string());
+    lines
+}
+
+fn format_date_time(date_time: DateTime<Utc>) -> String {
+    date_time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len());
+    let mut line_octets = 0;
+    for c in line.chars() {
+        // Never split a multi-byte character across two lines.
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+    folded
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_escape_text__special_characters() {
+        let escaped = escape_text("a,b;c\\d\ne");
+        assert_eq!(escaped, "a\\,b\\;c\\\\d\\ne");
+    }
+
+    #[test]
+    fn test_fold_line__short() {
+        assert_eq!(fold_line("SUMMARY:short"), "SUMMARY:short");
+    }
+
+    #[test]
+    fn test_fold_line__long() {
+        let line = format!("SUMMARY:{}", "a".repeat(100));
+        let folded = fold_line(&line);
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= MAX_LINE_OCTETS);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn test_fold_line__multi_byte() {
+        let line = format!("SUMMARY:{}", "’".repeat(40));
+        let folded = fold_line(&line);
+        for part in folded.split("\r\n") {
+            assert!(part.len() <= MAX_LINE_OCTETS);
+        }
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+
+    #[test]
+    fn test_render_todo__fields() {
+        let now = Utc.with_ymd_and_hms(2023, 3, 1, 12, 0, 0).unwrap();
+        let mut parent = db::Node::new();
+        parent.title = "Work".to_string();
+        let mut node = db::Node::new();
+        node.title = "Write report".to_string();
+        node.scheduled = Some(Utc.with_ymd_and_hms(2023, 3, 2, 9, 0, 0).unwrap());
+        node.due = Some(Utc.with_ymd_and_hms(2023, 3, 3, 17, 30, 0).unwrap());
+
+        let lines = render_todo(&node, &[parent], now);
+        assert!(lines.contains(&format!("UID:{}", node.id)));
+        assert!(lines.contains(&"DTSTAMP:20230301T120000Z".to_string()));
+        assert!(lines.contains(&"DTSTART:20230302T090000Z".to_string()));
+        assert!(lines.contains(&"DUE:20230303T173000Z".to_string()));
+        assert!(lines.contains(&"STATUS:NEEDS-ACTION".to_string()));
+        assert!(lines.contains(&"CATEGORIES:Work".to_string()));
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyEvent;
+use crossterm::event::KeyModifiers;
+use serde::Deserialize;
+use serde::Deserializer;
+use serde::Serialize;
+use serde::Serializer;
+
+/// Something a key can be bound to.
+/// These are written in the config in kebab-case, like `toggle-view`.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    Help,
+    Up,
+    Down,
+    Back,
+    Open,
+    Descend,
+    NextField,
+    PrevField,
+    Add,
+    Find,
+    Connect,
+    Next,
+    Parents,
+    Complete,
+    Trash,
+    ToggleView,
+    Cancel,
+    Confirm,
+    SwapDirection,
+    Suspend,
+    Edit,
+    Agenda,
+    Later,
+    Earlier,
+    Mark,
+    Visual,
+    ClearMarks,
+    Move,
+    Tag,
+    SetDue,
+    Inbox,
+    Schedule,
+    Review,
+    MarkReviewed,
+    Block,
+}
+
+impl Action {
+    pub fn description(self) -> &'static str {
+        use Action::*;
+        match self {
+            Quit => "quit",
+            Help => "show these keys",
+            Up => "move up",
+            Down => "move down",
+            Back => "go to the parent (collapse in the tree view)",
+            Open => "go into the selected node (expand in the tree view)",
+            Descend => "view the selected node's children",
+            NextField => "focus the next field",
+            PrevField => "focus the previous field",
+            Add => "add a node",
+            Find => "find a node",
+            Connect => "connect the selected node to another",
+            Next => "list next actions",
+            Parents => "list the selected node's parents",
+            Complete => "toggle completion",
+            Trash => "toggle trash",
+            ToggleView => "switch between the list and tree views",
+            Cancel => "cancel",
+            Confirm => "confirm",
+            SwapDirection => "swap direction",
+            Suspend => "suspend to the shell",
+            Edit => "edit the selected node in $EDITOR",
+            Agenda => "show scheduled and due nodes",
+            Later => "move a day later (the due date if it's passed)",
+            Earlier => "move a day earlier (the due date if it's passed)",
+            Mark => "mark or unmark the selected node",
+            Visual => "start or finish marking a range",
+            ClearMarks => "unmark everything",
+            Move => "move the marked nodes under another",
+            Tag => "tag the marked nodes",
+            SetDue => "set the marked nodes' due date",
+            Inbox => "process the inbox",
+            Schedule => "schedule the node",
+            Review => "start a weekly review",
+            MarkReviewed => "mark the node as reviewed",
+            Block => "pick a node that blocks the marked nodes",
+        }
+    }
+}
+
+/// A key along with its modifiers, written like `ctrl+f`, `shift+tab` or `?`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+const KEY_NAMES: [(&str, KeyCode); 15] = [
+    ("esc", KeyCode::Esc),
+    ("enter", KeyCode::Enter),
+    ("tab", KeyCode::Tab),
+    ("backtab", KeyCode::BackTab),
+    ("backspace", KeyCode::Backspace),
+    ("delete", KeyCode::Delete),
+    ("insert", KeyCode::Insert),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+];
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        // Shift is already part of which character (or BackTab) the terminal sends,
+        // and terminals disagree on whether to also report it as a modifier.
+        if let KeyCode::Char(_) | KeyCode::BackTab = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Key { code, modifiers }
+    }
+}
+
+impl From<KeyEvent> for Key {
+    fn from(evt: KeyEvent) -> Self {
+        Key::new(evt.code, evt.modifiers)
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Key::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+
+        let mut parts: Vec<&str> = s.split('+').collect();
+        let name = parts.pop().unwrap_or_default();
+        let mut modifiers = KeyModifiers::NONE;
+        for part in parts.into_iter() {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" | "meta" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(anyhow!("Unknown modifier {:?} in {:?}", part, s)),
+            };
+        }
+
+        let lower = name.to_lowercase();
+        let mut chars = name.chars();
+        let code = if let Some((_, code)) = KEY_NAMES.iter().find(|(name, _)| *name == lower) {
+            *code
+        } else if lower == "space" {
+            KeyCode::Char(' ')
+        } else if let (Some(c), None) = (chars.next(), chars.next()) {
+            KeyCode::Char(c)
+        } else if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+            KeyCode::F(n)
+        } else {
+            return Err(anyhow!("Unknown key {:?}", s));
+        };
+
+        if code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT) {
+            return Ok(Key::new(KeyCode::BackTab, modifiers));
+        }
+        Ok(Key::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift+")?;
+        }
+        match self.code {
+            KeyCode::BackTab => write!(f, "shift+tab"),
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "f{}", n),
+            code => match KEY_NAMES.iter().find(|(_, named)| *named == code) {
+                Some((name, _)) => write!(f, "{}", name),
+                None => write!(f, "{:?}", code),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Key {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Key {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+/// The keys bound to an action in the config, either as one key or a list of them.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Bindings {
+    One(Key),
+    Many(Vec<Key>),
+}
+
+impl Bindings {
+    pub fn keys(&self) -> Vec<Key> {
+        match self {
+            Bindings::One(key) => vec![*key],
+            Bindings::Many(keys) => keys.clone(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Preset {
+    #[default]
+    Default,
+    /// Adds hjkl movement, and `/` to find.
+    Vim,
+    /// Adds Ctrl+N/P/B/F movement, and Enter to confirm.
+    Emacs,
+}
+
+/// The `[keys]` section of the config.
+/// Each context maps actions to the keys which trigger them,
+/// replacing whatever the preset binds to that action in that context.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeysConfig {
+    pub preset: Preset,
+    pub global: BTreeMap<Action, Bindings>,
+    pub list: BTreeMap<Action, Bindings>,
+    pub editor: BTreeMap<Action, Bindings>,
+    pub add: BTreeMap<Action, Bindings>,
+    pub find: BTreeMap<Action, Bindings>,
+    pub connect: BTreeMap<Action, Bindings>,
+    pub next: BTreeMap<Action, Bindings>,
+    pub parents: BTreeMap<Action, Bindings>,
+    pub agenda: BTreeMap<Action, Bindings>,
+    pub prompt: BTreeMap<Action, Bindings>,
+    pub inbox: BTreeMap<Action, Bindings>,
+    pub review: BTreeMap<Action, Bindings>,
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key__round_trip() {
+        for key in ["ctrl+f", "shift+tab", "?", "esc", "alt+x", "f1", "space"] {
+            let parsed: Key = key.parse().unwrap();
+            assert_eq!(parsed.to_string(), key);
+        }
+        assert!("hyper+x".parse::<Key>().is_err());
+        assert!("nonsense".parse::<Key>().is_err());
+    }
+}
@@ -1,17 +1,26 @@
-use std::fs::File;
-use std::process::Stdio;
-use std::rc::Rc;
+use std::path::PathBuf;
 
 use chrono::DateTime;
 use chrono::Utc;
 use structopt::StructOpt;
-use uuid::Uuid;
 
 use db::Node;
-use db::NodeID;
+use node_ref::NodeRef;
 
+mod config;
 mod db;
+mod edit;
+mod export;
 mod find;
+mod ical;
+mod keys;
+mod node_ref;
+mod output;
+mod plan;
+mod report;
+mod review;
+mod stats;
+mod timer;
 mod ui;
 
 fn main() -> anyhow::Result<()> {
@@ -20,31 +29,109 @@ fn main() -> anyhow::Result<()> {
 
 async fn async_main() -> anyhow::Result<()> {
     let opt = Opt::from_args();
+    // Config commands shouldn't need a valid config (or database) to run,
+    // otherwise there'd be no way to fix a broken config with them.
+    if let Command::Config(args) = opt.command {
+        return config::main(args);
+    }
+
+    let config = config::Config::load()?;
+    let database_path = config.database_path(opt.db, opt.graph.as_deref())?;
+    if let Command::Init = opt.command {
+        return init(database_path).await;
+    }
 
-    let database = db::Database::default_new().await?;
-    match opt {
-        Opt::Add(args) => add(args, database).await,
-        Opt::Connect(args) => connect(args, database).await,
-        Opt::Edit(args) => edit(args, database).await,
-        Opt::Find(args) => find::main(args, database).await,
-        Opt::Next(args) => next(args, database).await,
-        Opt::Show(args) => show(args, database).await,
-        Opt::UI => ui::main(database).await,
+    let database = db::Database::open(&database_path).await?;
+    let format = opt.output.or(config.output).unwrap_or(output::Format::Text);
+    let mut output = output::Output::new(format);
+    let exact = opt.exact_ids;
+    match opt.command {
+        Command::Add(args) => add(args, database, &mut output).await,
+        Command::Capture(args) => capture(args, database, &mut output).await,
+        Command::Config(_) => unreachable!("Config is handled before loading the config"),
+        Command::Connect(args) => connect(args, database, exact).await,
+        Command::Disconnect(args) => disconnect(args, database, exact).await,
+        Command::Edit(args) => edit::main(args, database, &config, exact).await,
+        Command::Export(args) => export::main(args, database).await,
+        Command::Find(args) => find::main(args, database, &mut output).await,
+        Command::Ical(args) => ical::main(args, database).await,
+        Command::Init => unreachable!("Init is handled before opening the database"),
+        Command::Next(args) => next(args, database, &mut output, &config, exact).await,
+        Command::Plan(args) => plan::main(args, database, &mut output, exact).await,
+        Command::Report(args) => report::main(args, database, &mut output, exact).await,
+        Command::Review(args) => review::main(args, database, &mut output, exact).await,
+        Command::Show(args) => show(args, database, &mut output, exact).await,
+        Command::Start(args) => timer::start(args, database, &mut output, exact).await,
+        Command::Stats(args) => stats::main(args, database, &mut output, exact).await,
+        Command::Status => timer::status(database, &mut output).await,
+        Command::Stop => timer::stop(database, &mut output).await,
+        Command::UI => ui::main(database, config).await,
     }?;
+    output.finish()?;
     Ok(())
 }
 
 #[derive(Debug, StructOpt)]
-enum Opt {
+struct Opt {
+    /// How to print nodes: text, json, jsonl, or tsv. Defaults to text.
+    #[structopt(short = "o", long = "output", global = true)]
+    output: Option<output::Format>,
+    /// Path to the database. Overrides $TASQ_DB and the config file.
+    #[structopt(long = "db", global = true, parse(from_os_str))]
+    db: Option<PathBuf>,
+    /// Use a named graph instead of the default one.
+    #[structopt(short = "g", long = "graph", global = true, conflicts_with = "db")]
+    graph: Option<String>,
+    /// Only accept full uuids as node references, rather than prefixes, titles or @fuzzy text.
+    #[structopt(long = "exact-ids", global = true)]
+    exact_ids: bool,
+    #[structopt(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
     Add(AddArgs),
+    /// Add a node to the inbox, to be sorted out later.
+    Capture(CaptureArgs),
+    Config(config::Args),
     Connect(ConnectArgs),
-    Edit(EditArgs),
+    /// Remove the edge from one node to another.
+    Disconnect(ConnectArgs),
+    Edit(edit::Args),
+    Export(export::Args),
     Find(find::Args),
+    Ical(ical::Args),
+    /// Create a new, empty database.
+    Init,
     Show(ShowArgs),
     Next(NextArgs),
+    /// Work out when everything can get done from the estimates and scheduled dates,
+    /// marking the work each due date depends on and which due dates can't be met.
+    Plan(plan::Args),
+    Report(report::Args),
+    /// List what a weekly review should look at: stale projects, overdue and undated nodes,
+    /// and what's been completed this week.
+    Review(review::Args),
+    /// Summarize how far along each area and project is,
+    /// and whether any have stalled with nothing left to do.
+    Stats(stats::Args),
+    /// Start timing a node, stopping whatever timer was already running.
+    Start(timer::StartArgs),
+    /// Show what's being timed, and for how long.
+    Status,
+    /// Stop the running timer.
+    Stop,
     UI,
 }
 
+async fn init(database_path: PathBuf) -> anyhow::Result<()> {
+    let database = db::Database::create(&database_path).await?;
+    database.inbox().await?;
+    println!("{}", database_path.display());
+    Ok(())
+}
+
 #[derive(Debug, StructOpt)]
 struct AddArgs {
     #[structopt(short = "t", long = "title")]
@@ -55,9 +142,16 @@ struct AddArgs {
     scheduled: Option<chrono::NaiveDateTime>,
     #[structopt(short = "e", long = "due")]
     due: Option<chrono::NaiveDateTime>,
+    /// How long it should take, like `90m`, `2h` or `1h30m`.
+    #[structopt(long = "estimate", parse(try_from_str = plan::parse_estimate))]
+    estimate: Option<chrono::Duration>,
 }
 
-async fn add(args: AddArgs, database: db::Database) -> anyhow::Result<()> {
+async fn add(
+    args: AddArgs,
+    database: db::Database,
+    output: &mut output::Output,
+) -> anyhow::Result<()> {
     let mut node = Node::new();
     if let Some(title) = args.title {
         node.title = title;
@@ -71,85 +165,100 @@ async fn add(args: AddArgs, database: db::Database) -> anyhow::Result<()> {
     if let Some(due) = args.due {
         node.due = Some(DateTime::from_local(due, Utc));
     }
-    println!("{}", node.id.to_string());
+    node.estimate = args.estimate;
     database.add(&node).await?;
+    output.emit(output::Record::new(node), |record| record.node.id.to_string())?;
     Ok(())
 }
 
 #[derive(Debug, StructOpt)]
-struct ConnectArgs {
-    from: Uuid,
-    to: Uuid,
+struct CaptureArgs {
+    title: String,
+    #[structopt(short = "d", long = "description")]
+    description: Option<String>,
 }
 
-async fn connect(args: ConnectArgs, database: db::Database) -> anyhow::Result<()> {
-    database.connect(args.from, args.to).await?;
+async fn capture(
+    args: CaptureArgs,
+    database: db::Database,
+    output: &mut output::Output,
+) -> anyhow::Result<()> {
+    let mut node = Node::new();
+    node.title = args.title;
+    if let Some(description) = args.description {
+        node.description = description;
+    }
+    let inbox = database.inbox().await?;
+    database
+        .apply(&[
+            db::Change::Add(node.clone()),
+            db::Change::Connect(inbox, node.id),
+        ])
+        .await?;
+    output.emit(output::Record::new(node), |record| record.node.id.to_string())?;
     Ok(())
 }
 
 #[derive(Debug, StructOpt)]
-struct EditArgs {
-    node: NodeID,
+struct ConnectArgs {
+    from: NodeRef,
+    to: NodeRef,
+    /// Make `from` block `to`, so that `to` can't be started until `from` is done,
+    /// rather than making `to` a child of `from`.
+    #[structopt(short = "b", long = "blocks")]
+    blocks: bool,
 }
 
-async fn edit(args: EditArgs, database: db::Database) -> anyhow::Result<()> {
-    let editor = match std::env::var("EDITOR") {
-        Err(_) => "vi".to_string(),
-        Ok(editor) => editor,
-    };
-
-    let node = database.get_node(args.node).await?;
-
-    let temp_dir = tempfile::tempdir()?;
-    let mut filename = temp_dir.path().to_path_buf();
-    filename.push(format!("{}.json", node.id));
-
-    {
-        let file = File::create(&filename)?;
-        serde_json::to_writer_pretty::<_, Node>(file, &node)?;
-    };
-
-    let status = std::process::Command::new(editor)
-        .arg(&filename)
-        .stdin(Stdio::inherit())
-        .stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .status()?;
-
-    // If we fail to edit, just don't modify the database.
-    if !status.success() {
-        return Ok(());
+async fn connect(args: ConnectArgs, database: db::Database, exact: bool) -> anyhow::Result<()> {
+    let from = args.from.resolve(&database, exact).await?;
+    let to = args.to.resolve(&database, exact).await?;
+    if args.blocks {
+        database.block(from, to).await?;
+    } else {
+        database.connect(from, to).await?;
     }
+    Ok(())
+}
 
-    let node = {
-        let file = File::open(&filename)?;
-        serde_json::from_reader::<_, Rc<Node>>(file)?
+async fn disconnect(args: ConnectArgs, database: db::Database, exact: bool) -> anyhow::Result<()> {
+    let from = args.from.resolve(&database, exact).await?;
+    let to = args.to.resolve(&database, exact).await?;
+    let change = if args.blocks {
+        db::Change::Unblock(from, to)
+    } else {
+        db::Change::Disconnect(from, to)
     };
-    database.update(&node).await?;
-
-    Ok(())
+    database.apply(&[change]).await
 }
 
 #[derive(Debug, StructOpt)]
 struct NextArgs {
     #[structopt(short = "r", long = "root")]
-    root: Option<NodeID>,
+    root: Option<NodeRef>,
 }
 
-async fn next(args: NextArgs, database: db::Database) -> anyhow::Result<()> {
-    let to_next = if let Some(root_id) = args.root {
+async fn next(
+    args: NextArgs,
+    database: db::Database,
+    output: &mut output::Output,
+    config: &config::Config,
+    exact: bool,
+) -> anyhow::Result<()> {
+    let root = match args.root {
+        Some(root) => Some(root.resolve(&database, exact).await?),
+        None => config.next.root,
+    };
+    let to_next = if let Some(root_id) = root {
         vec![root_id]
     } else {
         database.get_roots().await?
     };
 
-    for root in to_next.into_iter() {
-        let mut dfs = database.dfs(root).await?;
-        while let Some((node, _)) = dfs.next().await? {
-            if !database.has_children(node).await? {
-                println!("{}", database.get_node(node).await?.short_repr());
-            }
-        }
+    for action in database.next_actions(&to_next).await?.into_iter() {
+        let record = output::Record::new(action.node)
+            .with_depth(action.path.len())
+            .with_path(&action.path);
+        output.emit(record, |record| record.node.short_repr())?;
     }
 
     Ok(())
@@ -158,23 +267,54 @@ async fn next(args: NextArgs, database: db::Database) -> anyhow::Result<()> {
 #[derive(Debug, StructOpt)]
 struct ShowArgs {
     #[structopt(short = "r", long = "root")]
-    root: Option<NodeID>,
+    root: Option<NodeRef>,
 }
 
-async fn show(args: ShowArgs, database: db::Database) -> anyhow::Result<()> {
-    let to_show = if let Some(root_id) = args.root {
-        vec![root_id]
+async fn show(
+    args: ShowArgs,
+    database: db::Database,
+    output: &mut output::Output,
+    exact: bool,
+) -> anyhow::Result<()> {
+    let to_show = if let Some(root) = args.root {
+        vec![root.resolve(&database, exact).await?]
     } else {
         database.get_roots().await?
     };
 
     for root in to_show.into_iter() {
+        let mut path = Vec::new();
         let mut dfs = database.dfs(root).await?;
         while let Some((node, depth)) = dfs.next().await? {
-            for _ in 0..2 * depth {
-                print!(" ");
+            let node = database.get_node(node).await?;
+            path.truncate(depth);
+            let kind = database.kind(node.id).await?;
+            let mut record = output::Record::new(node.clone())
+                .with_depth(depth)
+                .with_path(&path)
+                .with_kind(kind)
+                .with_blocked_by(&database.get_open_blockers(node.id).await?);
+            if let db::Kind::Area | db::Kind::Project = kind {
+                record = record.with_stats(database.stats(node.id).await?);
             }
-            println!("{}", database.get_node(node).await?.short_repr());
+            output.emit(record, |record| {
+                let mut text = format!(
+                    "{}{} {}",
+                    " ".repeat(2 * depth),
+                    record.node.short_repr(),
+                    stats::describe(record)
+                );
+                if !record.blocked_by.is_empty() {
+                    let blockers: Vec<&str> = record
+                        .blocked_by
+                        .iter()
+                        .map(|entry| entry.title.as_str())
+                        .collect();
+                    text.push_str(&format!(" blocked by {}", blockers.join(", ")));
+                }
+                text
+            })?;
+            path.push(node);
         }
     }
 
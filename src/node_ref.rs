@@ -0,0 +1,138 @@
+use std::convert::Infallible;
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use uuid::Uuid;
+
+use crate::db;
+use crate::find::find_candidates;
+
+/// A reference to a node on the command line. This can be:
+///
+/// - a full uuid,
+/// - a unique prefix of a uuid (like git's abbreviated hashes),
+/// - a node's exact title,
+/// - or `@some text`, which picks the best match from a fuzzy find.
+#[derive(Clone, Debug)]
+pub enum NodeRef {
+    Id(db::NodeID),
+    Text(String),
+    Fuzzy(String),
+}
+
+impl FromStr for NodeRef {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(fuzzy) = s.strip_prefix('@') {
+            return Ok(NodeRef::Fuzzy(fuzzy.to_string()));
+        }
+        if let Ok(id) = Uuid::try_parse(s) {
+            return Ok(NodeRef::Id(id));
+        }
+        Ok(NodeRef::Text(s.to_string()))
+    }
+}
+
+impl NodeRef {
+    /// Finds the node this refers to, failing if there isn't exactly one.
+    /// When `exact` is set only full uuids are accepted,
+    /// so that scripts can't accidentally act on the wrong node.
+    pub async fn resolve(
+        &self,
+        database: &db::Database,
+        exact: bool,
+    ) -> anyhow::Result<db::NodeID> {
+        let (text, matches) = match self {
+            NodeRef::Id(id) => return Ok(*id),
+            _ if exact => {
+                return Err(anyhow!(
+                    "{} is not a full uuid, which --exact-ids requires",
+                    self
+                ))
+            }
+            NodeRef::Text(text) => (text, text_matches(text, database).await?),
+            NodeRef::Fuzzy(text) => (text, fuzzy_matches(text, database).await?),
+        };
+
+        match matches.as_slice() {
+            [] => Err(anyhow!("No node matches {}", self)),
+            [id] => Ok(*id),
+            _ => {
+                let mut message = format!("{:?} is ambiguous; it could refer to:", text);
+                for id in matches.iter() {
+                    message.push_str("\n  ");
+                    message.push_str(&database.get_node(*id).await?.short_repr());
+                }
+                Err(anyhow!(message))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for NodeRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NodeRef::Id(id) => write!(f, "{}", id),
+            NodeRef::Text(text) => write!(f, "{:?}", text),
+            NodeRef::Fuzzy(text) => write!(f, "{:?}", format!("@{}", text)),
+        }
+    }
+}
+
+async fn text_matches(text: &str, database: &db::Database) -> anyhow::Result<Vec<db::NodeID>> {
+    let mut matches = Vec::new();
+    if looks_like_id_prefix(text) {
+        matches.extend(database.get_nodes_by_id_prefix(text).await?);
+    }
+    for id in database.get_nodes_by_title(text).await?.into_iter() {
+        if !matches.contains(&id) {
+            matches.push(id);
+        }
+    }
+    Ok(matches)
+}
+
+async fn fuzzy_matches(text: &str, database: &db::Database) -> anyhow::Result<Vec<db::NodeID>> {
+    let candidates = find_candidates(text, database).await?;
+    let Some(best) = candidates.iter().map(|(_, distance)| *distance).min() else {
+        return Ok(vec![]);
+    };
+    Ok(candidates
+        .into_iter()
+        .filter(|(_, distance)| *distance == best)
+        .map(|(node, _)| node.id)
+        .collect())
+}
+
+fn looks_like_id_prefix(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| c.is_ascii_hexdigit() || c == '-')
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str__full_uuid() {
+        let id = Uuid::new_v4();
+        let node_ref: NodeRef = id.to_string().parse().unwrap();
+        assert!(matches!(node_ref, NodeRef::Id(parsed) if parsed == id));
+    }
+
+    #[test]
+    fn test_from_str__fuzzy() {
+        let node_ref: NodeRef = "@groceries".parse().unwrap();
+        assert!(matches!(node_ref, NodeRef::Fuzzy(text) if text == "groceries"));
+    }
+
+    #[test]
+    fn test_looks_like_id_prefix() {
+        assert!(looks_like_id_prefix("3f2a"));
+        assert!(looks_like_id_prefix("3f2a9c1e-77"));
+        assert!(!looks_like_id_prefix("Groceries"));
+        assert!(!looks_like_id_prefix("3f%"));
+        assert!(!looks_like_id_prefix(""));
+    }
+}
@@ -0,0 +1,370 @@
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use chrono::serde::ts_seconds_option;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Local;
+use chrono::SecondsFormat;
+use chrono::Utc;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::db;
+use crate::plan;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    Text,
+    Json,
+    Jsonl,
+    Tsv,
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "jsonl" => Ok(Format::Jsonl),
+            "tsv" => Ok(Format::Tsv),
+            _ => Err(anyhow!("Unknown output format {}", s)),
+        }
+    }
+}
+
+/// A node along with whatever context the subcommand that found it knows about.
+/// Fields which don't apply to a subcommand are still emitted (as `null`)
+/// so that consumers can rely on the same set of keys everywhere.
+#[derive(Debug, Serialize)]
+pub struct Record {
+    #[serde(flatten)]
+    pub node: db::Node,
+    pub depth: Option<usize>,
+    pub path: Vec<PathEntry>,
+    pub score: Option<usize>,
+    /// Which part of a review the node turned up in.
+    pub category: Option<&'static str>,
+    /// When the node was last marked as reviewed.
+    #[serde(with = "ts_seconds_option")]
+    pub reviewed: Option<DateTime<Utc>>,
+    /// What the node is, going by where it sits in the graph.
+    pub kind: Option<db::Kind>,
+    /// How far along everything beneath the node is.
+    pub stats: Option<db::Stats>,
+    /// The open nodes which have to be done before this one can be started.
+    pub blocked_by: Vec<PathEntry>,
+    /// When the node can get done, as `tasq plan` works it out.
+    pub plan: Option<plan::Step>,
+    /// How long has been spent on the node, and everything beneath it where that applies.
+    #[serde(with = "db::minutes_option")]
+    pub tracked: Option<Duration>,
+}
+
+impl Record {
+    pub fn new(node: db::Node) -> Self {
+        Self {
+            node,
+            depth: None,
+            path: vec![],
+            score: None,
+            category: None,
+            reviewed: None,
+            kind: None,
+            stats: None,
+            blocked_by: vec![],
+            plan: None,
+            tracked: None,
+        }
+    }
+
+    pub fn with_depth(mut self, depth: usize) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    pub fn with_path(mut self, path: &[db::Node]) -> Self {
+        self.path = path.iter().map(PathEntry::from).collect();
+        self
+    }
+
+    pub fn with_score(mut self, score: usize) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    pub fn with_category(mut self, category: &'static str) -> Self {
+        self.category = Some(category);
+        self
+    }
+
+    pub fn with_reviewed(mut self, reviewed: Option<DateTime<Utc>>) -> Self {
+        self.reviewed = reviewed;
+        self
+    }
+
+    pub fn with_kind(mut self, kind: db::Kind) -> Self {
+        self.kind = Some(kind);
+        self
+    }
+
+    pub fn with_stats(mut self, stats: db::Stats) -> Self {
+        self.stats = Some(stats);
+        self
+    }
+
+    pub fn with_blocked_by(mut self, blockers: &[db::Node]) -> Self {
+        self.blocked_by = blockers.iter().map(PathEntry::from).collect();
+        self
+    }
+
+    pub fn with_plan(mut self, step: plan::Step) -> Self {
+        self.plan = Some(step);
+        self
+    }
+
+    pub fn with_tracked(mut self, tracked: Duration) -> Self {
+        self.tracked = Some(tracked);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PathEntry {
+    pub id: db::NodeID,
+    pub title: String,
+}
+
+impl From<&db::Node> for PathEntry {
+    fn from(node: &db::Node) -> Self {
+        Self {
+            id: node.id,
+            title: node.title.clone(),
+        }
+    }
+}
+
+const TSV_COLUMNS: [&str; 24] = [
+    "id",
+    "title",
+    "description",
+    "scheduled",
+    "due",
+    "completed",
+    "trashed",
+    "depth",
+    "path",
+    "score",
+    "category",
+    "reviewed",
+    "kind",
+    "open_leaves",
+    "completion",
+    "next_due",
+    "stalled",
+    "blocked_by",
+    "estimate",
+    "start",
+    "finish",
+    "critical",
+    "late",
+    "tracked",
+];
+
+pub struct Output {
+    format: Format,
+    records: Vec<Record>,
+    wrote_header: bool,
+}
+
+impl Output {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            records: vec![],
+            wrote_header: false,
+        }
+    }
+
+    /// Emits a single record.
+    /// `text` is what gets printed in the human-readable `text` format,
+    /// since each subcommand has its own idea of what that should look like.
+    pub fn emit(
+        &mut self,
+        record: Record,
+        text: impl FnOnce(&Record) -> String,
+    ) -> anyhow::Result<()> {
+        match self.format {
+            Format::Text => println!("{}", text(&record)),
+            // JSON output is a single array, so we can't write anything until we've seen every record.
+            Format::Json => self.records.push(record),
+            Format::Jsonl => println!("{}", serde_json::to_string(&record)?),
+            Format::Tsv => {
+                if !self.wrote_header {
+                    println!("{}", TSV_COLUMNS.join("\t"));
+                    self.wrote_header = true;
+                }
+                println!("{}", tsv_row(&record).join("\t"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        if let Format::Json = self.format {
+            println!("{}", serde_json::to_string_pretty(&self.records)?);
+        }
+        Ok(())
+    }
+}
+
+/// Formats a time for text output in local time, the same as the UI shows it.
+pub fn format_local(date_time: DateTime<Utc>) -> String {
+    date_time
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
+
+fn tsv_row(record: &Record) -> Vec<String> {
+    let node = &record.node;
+    let path: Vec<&str> = record
+        .path
+        .iter()
+        .map(|entry| entry.title.as_str())
+        .collect();
+    let stats = record.stats.as_ref();
+    let step = record.plan.as_ref();
+    let blocked_by: Vec<&str> = record
+        .blocked_by
+        .iter()
+        .map(|entry| entry.title.as_str())
+        .collect();
+    vec![
+        node.id.to_string(),
+        tsv_escape(&node.title),
+        tsv_escape(&node.description),
+        tsv_date_time(node.scheduled),
+        tsv_date_time(node.due),
+        tsv_date_time(node.completed),
+        node.trashed.to_string(),
+        record
+            .depth
+            .map(|depth| depth.to_string())
+            .unwrap_or_default(),
+        tsv_escape(&path.join("/")),
+        record
+            .score
+            .map(|score| score.to_string())
+            .unwrap_or_default(),
+        record.category.unwrap_or_default().to_string(),
+        tsv_date_time(record.reviewed),
+        record
+            .kind
+            .map(db::Kind::name)
+            .unwrap_or_default()
+            .to_string(),
+        stats
+            .map(|stats| stats.open_leaves.to_string())
+            .unwrap_or_default(),
+        stats
+            .and_then(db::Stats::completion)
+            .map(|completion| completion.to_string())
+            .unwrap_or_default(),
+        tsv_date_time(stats.and_then(|stats| stats.next_due)),
+        stats
+            .map(|stats| stats.stalled.to_string())
+            .unwrap_or_default(),
+        tsv_escape(&blocked_by.join(", ")),
+        node.estimate
+            .map(|estimate| estimate.num_minutes().to_string())
+            .unwrap_or_default(),
+        tsv_date_time(step.map(|step| step.start)),
+        tsv_date_time(step.map(|step| step.finish)),
+        step.map(|step| step.critical.to_string())
+            .unwrap_or_default(),
+        step.map(|step| step.late.to_string()).unwrap_or_default(),
+        record
+            .tracked
+            .map(|tracked| tracked.num_minutes().to_string())
+            .unwrap_or_default(),
+    ]
+}
+
+fn tsv_date_time(date_time: Option<DateTime<Utc>>) -> String {
+    date_time
+        .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+fn tsv_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tsv_escape__control_characters() {
+        assert_eq!(tsv_escape("a\tb\nc\\d"), "a\\tb\\nc\\\\d");
+    }
+
+    #[test]
+    fn test_record__stable_fields() {
+        let record = Record::new(db::Node::new());
+        let value = serde_json::to_value(&record).unwrap();
+        let mut keys: Vec<&String> = value.as_object().unwrap().keys().collect();
+        keys.sort();
+        assert_eq!(
+            keys,
+            vec![
+                "blocked_by",
+                "category",
+                "completed",
+                "depth",
+                "description",
+                "due",
+                "estimate",
+                "id",
+                "kind",
+                "path",
+                "plan",
+                "reviewed",
+                "scheduled",
+                "score",
+                "stats",
+                "title",
+                "tracked",
+                "trashed",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_tsv_row__matches_columns() {
+        let mut parent = db::Node::new();
+        parent.title = "Work".to_string();
+        let record = Record::new(db::Node::new())
+            .with_depth(1)
+            .with_path(&[parent]);
+        let row = tsv_row(&record);
+        assert_eq!(row.len(), TSV_COLUMNS.len());
+        assert_eq!(row[7], "1");
+        assert_eq!(row[8], "Work");
+    }
+}
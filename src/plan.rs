@@ -0,0 +1,332 @@
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::VecDeque;
+
+use anyhow::anyhow;
+use chrono::serde::ts_seconds;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use serde::Serialize;
+use structopt::StructOpt;
+
+use crate::db;
+use crate::node_ref::NodeRef;
+use crate::output;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only plan the work beneath this node, along with whatever blocks it.
+    #[structopt(short = "r", long = "root")]
+    root: Option<NodeRef>,
+}
+
+pub async fn main(
+    args: Args,
+    database: db::Database,
+    output: &mut output::Output,
+    exact: bool,
+) -> anyhow::Result<()> {
+    let roots = match args.root {
+        Some(root) => vec![root.resolve(&database, exact).await?],
+        None => database.get_roots().await?,
+    };
+
+    let (nodes, waits_on) = gather(&database, &roots).await?;
+    for (node, step) in schedule(nodes, &waits_on, Utc::now())?.into_iter() {
+        let is_leaf = database.get_children(node.id).await?.is_empty();
+        let record = output::Record::new(node).with_plan(step);
+        output.emit(record, |record| {
+            let Some(step) = &record.plan else { return String::new() };
+            let mut text = format!(
+                "{}  {}  {}{}",
+                output::format_local(step.start),
+                output::format_local(step.finish),
+                if step.critical { "* " } else { "  " },
+                record.node.short_repr(),
+            );
+            match record.node.estimate {
+                Some(estimate) => text.push_str(&format!(" [{}]", format_estimate(estimate))),
+                // Nodes with children take as long as those do,
+                // but leaves without an estimate are planned as if they took no time.
+                None if is_leaf => text.push_str(" [no estimate]"),
+                None => {}
+            }
+            if let Some(due) = record.node.due {
+                text.push_str(&format!(" due {}", output::format_local(due)));
+                if step.late {
+                    text.push_str(&format!(", {} late", format_estimate(step.finish - due)));
+                }
+            }
+            text
+        })?;
+    }
+    Ok(())
+}
+
+/// When a node can get done, if everything before it goes to plan.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Step {
+    #[serde(with = "ts_seconds")]
+    pub start: DateTime<Utc>,
+    #[serde(with = "ts_seconds")]
+    pub finish: DateTime<Utc>,
+    /// Whether this is on the chain of work which decides when a due node can be finished.
+    pub critical: bool,
+    /// Whether this can't be finished by its due date.
+    pub late: bool,
+}
+
+/// Collects the open nodes beneath `roots`, along with anything blocking them,
+/// and what each of them has to wait for:
+/// a node can't be finished before its children are, or started before its blockers are done.
+async fn gather(
+    database: &db::Database,
+    roots: &[db::NodeID],
+) -> anyhow::Result<(Vec<db::Node>, BTreeMap<db::NodeID, Vec<db::NodeID>>)> {
+    let mut seen = BTreeSet::new();
+    let mut nodes = Vec::new();
+    let mut waits_on = BTreeMap::new();
+    let mut stack: Vec<db::NodeID> = roots.iter().rev().copied().collect();
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let node = database.get_node(id).await?;
+        if !node.is_open() {
+            continue;
+        }
+
+        let mut before = database.get_children(id).await?;
+        before.extend(database.get_blockers(id).await?);
+        stack.extend(before.iter().rev());
+        waits_on.insert(id, before);
+        nodes.push(node);
+    }
+
+    // Closed nodes are already done, so they don't hold anything up.
+    let open: BTreeSet<db::NodeID> = nodes.iter().map(|node| node.id).collect();
+    for before in waits_on.values_mut() {
+        before.retain(|id| open.contains(id));
+    }
+    Ok((nodes, waits_on))
+}
+
+/// Works out the earliest each node can start and finish,
+/// in an order where everything a node waits on comes before it.
+/// Nothing starts before `now` or before it's scheduled,
+/// and work is assumed to happen in parallel wherever the graph allows.
+fn schedule(
+    nodes: Vec<db::Node>,
+    waits_on: &BTreeMap<db::NodeID, Vec<db::NodeID>>,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Vec<(db::Node, Step)>> {
+    let index: BTreeMap<db::NodeID, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| (node.id, i))
+        .collect();
+    let before = |i: usize| -> Vec<usize> {
+        waits_on
+            .get(&nodes[i].id)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| index.get(id).copied())
+            .collect()
+    };
+
+    let mut waiting: Vec<usize> = (0..nodes.len()).map(|i| before(i).len()).collect();
+    let mut unblocks = vec![Vec::new(); nodes.len()];
+    for i in 0..nodes.len() {
+        for j in before(i).into_iter() {
+            unblocks[j].push(i);
+        }
+    }
+    let mut ready: VecDeque<usize> = (0..nodes.len()).filter(|i| waiting[*i] == 0).collect();
+    let mut order = Vec::new();
+    while let Some(i) = ready.pop_front() {
+        order.push(i);
+        for j in unblocks[i].iter() {
+            waiting[*j] -= 1;
+            if waiting[*j] == 0 {
+                ready.push_back(*j);
+            }
+        }
+    }
+    if order.len() < nodes.len() {
+        return Err(anyhow!(
+            "The graph has a cycle in it, so it can't be planned"
+        ));
+    }
+
+    let mut starts = vec![now; nodes.len()];
+    let mut finishes = vec![now; nodes.len()];
+    // Which of the nodes that each one waits on it's waiting on the longest.
+    let mut drivers = vec![None; nodes.len()];
+    for i in order.iter().copied() {
+        let mut start = nodes[i]
+            .scheduled
+            .map_or(now, |scheduled| scheduled.max(now));
+        for j in before(i).into_iter() {
+            if finishes[j] > start {
+                start = finishes[j];
+                drivers[i] = Some(j);
+            }
+        }
+        starts[i] = start;
+        finishes[i] = start + nodes[i].estimate.unwrap_or_else(Duration::zero);
+    }
+
+    let mut critical = vec![false; nodes.len()];
+    for i in (0..nodes.len()).filter(|i| nodes[*i].due.is_some()) {
+        let mut current = Some(i);
+        while let Some(j) = current {
+            // The rest of the chain has already been walked from another due node.
+            if critical[j] {
+                break;
+            }
+            critical[j] = true;
+            current = drivers[j];
+        }
+    }
+
+    let mut nodes: Vec<Option<db::Node>> = nodes.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .filter_map(|i| {
+            let node = nodes[i].take()?;
+            let step = Step {
+                start: starts[i],
+                finish: finishes[i],
+                critical: critical[i],
+                late: node.due.is_some_and(|due| finishes[i] > due),
+            };
+            Some((node, step))
+        })
+        .collect())
+}
+
+/// Parses an estimate like `90m`, `2h`, `1h30m` or `3d`.
+/// A bare number is a number of minutes.
+pub fn parse_estimate(text: &str) -> anyhow::Result<Duration> {
+    let text = text.trim();
+    if let Ok(minutes) = text.parse::<i64>() {
+        if minutes < 0 {
+            return Err(anyhow!("Estimates can't be negative, but got {:?}", text));
+        }
+        return Ok(Duration::minutes(minutes));
+    }
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let amount: i64 = number
+            .parse()
+            .map_err(|_| anyhow!("Couldn't parse estimate {:?}", text))?;
+        total = total
+            + match c {
+                'd' => Duration::days(amount),
+                'h' => Duration::hours(amount),
+                'm' => Duration::minutes(amount),
+                _ => return Err(anyhow!("Unknown unit {:?} in estimate {:?}", c, text)),
+            };
+        number.clear();
+    }
+    if !number.is_empty() || text.is_empty() {
+        return Err(anyhow!("Couldn't parse estimate {:?}", text));
+    }
+    Ok(total)
+}
+
+/// Writes a duration the way [`parse_estimate`] reads it, to the nearest minute.
+pub fn format_estimate(duration: Duration) -> String {
+    let minutes = duration.num_minutes();
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    let mut text = String::new();
+    if days > 0 {
+        text.push_str(&format!("{}d", days));
+    }
+    if hours > 0 {
+        text.push_str(&format!("{}h", hours));
+    }
+    if minutes > 0 || text.is_empty() {
+        text.push_str(&format!("{}m", minutes));
+    }
+    text
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn node(title: &str, estimate: Option<Duration>) -> db::Node {
+        let mut node = db::Node::new();
+        node.title = title.to_string();
+        node.estimate = estimate;
+        node
+    }
+
+    #[test]
+    fn test_parse_estimate__units() -> anyhow::Result<()> {
+        assert_eq!(parse_estimate("90")?, Duration::minutes(90));
+        assert_eq!(parse_estimate("1h30m")?, Duration::minutes(90));
+        assert_eq!(parse_estimate("2d")?, Duration::days(2));
+        assert!(parse_estimate("3 weeks").is_err());
+        assert!(parse_estimate("1h30").is_err());
+        assert!(parse_estimate("-30").is_err());
+        assert!(parse_estimate("-1h").is_err());
+        assert_eq!(format_estimate(Duration::minutes(24 * 60 + 90)), "1d1h30m");
+        assert_eq!(format_estimate(Duration::zero()), "0m");
+        Ok(())
+    }
+
+    #[test]
+    fn test_schedule__critical_path() -> anyhow::Result<()> {
+        let now = Utc.with_ymd_and_hms(2026, 10, 19, 9, 0, 0).unwrap();
+        let short = node("short", Some(Duration::hours(1)));
+        let long = node("long", Some(Duration::hours(3)));
+        let mut project = node("project", None);
+        project.due = Some(now + Duration::hours(2));
+        let mut later = node("later", Some(Duration::hours(1)));
+        later.scheduled = Some(now + Duration::days(1));
+
+        let waits_on = BTreeMap::from([
+            (project.id, vec![short.id, long.id]),
+            (short.id, vec![]),
+            (long.id, vec![]),
+            (later.id, vec![]),
+        ]);
+        let steps = schedule(
+            vec![project.clone(), short.clone(), long.clone(), later.clone()],
+            &waits_on,
+            now,
+        )?;
+        let steps: BTreeMap<db::NodeID, Step> = steps
+            .into_iter()
+            .map(|(node, step)| (node.id, step))
+            .collect();
+
+        assert_eq!(steps[&project.id].start, now + Duration::hours(3));
+        assert!(steps[&project.id].late);
+        assert!(steps[&long.id].critical);
+        assert!(!steps[&short.id].critical);
+        assert_eq!(steps[&later.id].finish, now + Duration::hours(25));
+        assert!(!steps[&later.id].critical);
+        Ok(())
+    }
+
+    #[test]
+    fn test_schedule__cycle() {
+        let a = node("a", None);
+        let b = node("b", None);
+        let waits_on = BTreeMap::from([(a.id, vec![b.id]), (b.id, vec![a.id])]);
+        assert!(schedule(vec![a, b], &waits_on, Utc::now()).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::TimeZone;
+use chrono::Utc;
+use structopt::StructOpt;
+
+use crate::db;
+use crate::node_ref::NodeRef;
+use crate::output;
+use crate::timer;
+
+#[derive(Debug, StructOpt)]
+pub enum Args {
+    /// Add up the time tracked on each node, along with everything beneath it.
+    Time(TimeArgs),
+}
+
+#[derive(Debug, StructOpt)]
+pub struct TimeArgs {
+    /// Only count time from the start of this day, like `2026-10-01`.
+    #[structopt(long = "since")]
+    since: Option<NaiveDate>,
+    /// Only count time up to the end of this day.
+    #[structopt(long = "until")]
+    until: Option<NaiveDate>,
+    /// Only report on this node and what's beneath it.
+    #[structopt(short = "r", long = "root")]
+    root: Option<NodeRef>,
+}
+
+pub async fn main(
+    args: Args,
+    database: db::Database,
+    output: &mut output::Output,
+    exact: bool,
+) -> anyhow::Result<()> {
+    match args {
+        Args::Time(args) => time(args, database, output, exact).await,
+    }
+}
+
+async fn time(
+    args: TimeArgs,
+    database: db::Database,
+    output: &mut output::Output,
+    exact: bool,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let since = args.since.map(start_of_day);
+    // Running timers only count up to now, even when reporting on days still to come.
+    let until = args.until.map_or(now, |until| {
+        start_of_day(until + Duration::days(1)).min(now)
+    });
+
+    let mut own = BTreeMap::new();
+    for entry in database.get_time_entries(since, until).await?.into_iter() {
+        let tracked = own.entry(entry.node).or_insert_with(Duration::zero);
+        *tracked = *tracked + entry.duration_between(since, until);
+    }
+
+    let roots = match args.root {
+        Some(root) => vec![root.resolve(&database, exact).await?],
+        None => database.get_roots().await?,
+    };
+    for root in roots.into_iter() {
+        let mut children = BTreeMap::new();
+        let mut dfs = database.dfs(root).await?;
+        while let Some((id, _)) = dfs.next().await? {
+            if let Entry::Vacant(entry) = children.entry(id) {
+                entry.insert(database.get_children(id).await?);
+            }
+        }
+
+        // Each root gets a report of its own,
+        // so time under a node shared between roots counts towards both of them.
+        let mut path = Vec::new();
+        let mut dfs = database.dfs(root).await?;
+        while let Some((id, depth)) = dfs.next().await? {
+            path.truncate(depth);
+            let node = database.get_node(id).await?;
+            let tracked = rollup(id, &children, &own);
+            // Nothing beneath a node with no time has any time either.
+            if tracked > Duration::zero() {
+                let record = output::Record::new(node.clone())
+                    .with_depth(depth)
+                    .with_path(&path)
+                    .with_tracked(tracked);
+                output.emit(record, |record| {
+                    format!(
+                        "{}{} {}",
+                        " ".repeat(2 * depth),
+                        record.node.short_repr(),
+                        timer::format_elapsed(tracked)
+                    )
+                })?;
+            }
+            path.push(node);
+        }
+    }
+    Ok(())
+}
+
+/// When `date` starts in local time.
+fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    // Clocks going forward can skip midnight, in which case the day starts once they have.
+    (0..24)
+        .find_map(|hour| {
+            Local
+                .from_local_datetime(&date.and_hms_opt(hour, 0, 0)?)
+                .earliest()
+        })
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+/// The time spent on `id` and everything beneath it.
+/// Nodes reachable along more than one path are only counted once.
+fn rollup(
+    id: db::NodeID,
+    children: &BTreeMap<db::NodeID, Vec<db::NodeID>>,
+    own: &BTreeMap<db::NodeID, Duration>,
+) -> Duration {
+    let mut seen = BTreeSet::new();
+    let mut stack = vec![id];
+    let mut total = Duration::zero();
+    while let Some(id) = stack.pop() {
+        if !seen.insert(id) {
+            continue;
+        }
+        if let Some(tracked) = own.get(&id) {
+            total = total + *tracked;
+        }
+        stack.extend(children.get(&id).into_iter().flatten());
+    }
+    total
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_rollup__shared_descendants_count_once() {
+        let (root, a, b, shared) = (
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+        );
+        let children = BTreeMap::from([
+            (root, vec![a, b]),
+            (a, vec![shared]),
+            (b, vec![shared]),
+            (shared, vec![]),
+        ]);
+        let own = BTreeMap::from([(a, Duration::minutes(30)), (shared, Duration::hours(1))]);
+
+        assert_eq!(rollup(root, &children, &own), Duration::minutes(90));
+        assert_eq!(rollup(a, &children, &own), Duration::minutes(90));
+        assert_eq!(rollup(b, &children, &own), Duration::hours(1));
+    }
+}
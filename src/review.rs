@@ -0,0 +1,245 @@
+use std::collections::BTreeSet;
+
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Utc;
+use structopt::StructOpt;
+
+use crate::db;
+use crate::node_ref::NodeRef;
+use crate::output;
+
+/// How far back a review looks for completed nodes.
+const REVIEW_PERIOD_DAYS: i64 = 7;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Record these projects as reviewed now, instead of listing what needs reviewing.
+    #[structopt(long = "mark")]
+    mark: Vec<NodeRef>,
+}
+
+pub async fn main(
+    args: Args,
+    database: db::Database,
+    output: &mut output::Output,
+    exact: bool,
+) -> anyhow::Result<()> {
+    if !args.mark.is_empty() {
+        let now = Utc::now();
+        for node in args.mark.iter() {
+            database
+                .set_reviewed(node.resolve(&database, exact).await?, now)
+                .await?;
+        }
+        return Ok(());
+    }
+
+    let mut last_category = None;
+    for item in gather(&database, Utc::now()).await?.into_iter() {
+        // Text output is grouped under headings, since there's no category column to read.
+        let heading = if last_category != Some(item.category) {
+            last_category = Some(item.category);
+            Some(item.category.title())
+        } else {
+            None
+        };
+        let record = output::Record::new(item.node)
+            .with_path(&item.path)
+            .with_category(item.category.name())
+            .with_reviewed(item.reviewed);
+        output.emit(record, |record| {
+            let mut text = String::new();
+            if let Some(heading) = heading {
+                text.push_str(heading);
+                text.push('\n');
+            }
+            text.push_str(&format!("  {}", record.node.short_repr()));
+            if item.category == Category::Stale {
+                match record.reviewed {
+                    Some(reviewed) => {
+                        text.push_str(&format!(" last reviewed {}", reviewed.format("%Y-%m-%d")))
+                    }
+                    None => text.push_str(" never reviewed"),
+                }
+            }
+            text
+        })?;
+    }
+    Ok(())
+}
+
+/// What a weekly review looks at, in the order it looks at them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    /// Open nodes with children, none of which are open,
+    /// so there's nothing left to do to move them forward.
+    Stale,
+    Overdue,
+    /// Open leaves without a due date.
+    Undated,
+    /// Nodes completed within the last week.
+    Completed,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        use Category::*;
+        match self {
+            Stale => "stale",
+            Overdue => "overdue",
+            Undated => "undated",
+            Completed => "completed",
+        }
+    }
+
+    pub fn title(self) -> &'static str {
+        use Category::*;
+        match self {
+            Stale => "Stale projects",
+            Overdue => "Overdue",
+            Undated => "No due date",
+            Completed => "Completed this week",
+        }
+    }
+}
+
+pub struct ReviewItem {
+    pub category: Category,
+    pub node: db::Node,
+    /// Ancestors of `node`, from the root it was found under down to its parent.
+    pub path: Vec<db::Node>,
+    /// When `node` was last marked as reviewed.
+    pub reviewed: Option<DateTime<Utc>>,
+}
+
+/// Walks the whole graph, picking out what a weekly review should look at.
+/// Items are grouped by category, with the projects which have gone longest without a review
+/// and the most overdue nodes first.
+pub async fn gather(
+    database: &db::Database,
+    now: DateTime<Utc>,
+) -> anyhow::Result<Vec<ReviewItem>> {
+    let reviewed = database.get_reviewed().await?;
+    let mut seen = BTreeSet::new();
+    let mut items = Vec::new();
+    for root in database.get_roots().await?.into_iter() {
+        let mut path: Vec<db::Node> = Vec::new();
+        let mut dfs = database.dfs(root).await?;
+        while let Some((id, depth)) = dfs.next().await? {
+            path.truncate(depth);
+            let node = database.get_node(id).await?;
+            // Nodes with several parents are found once through each of them.
+            if seen.insert(id) {
+                let mut children = Vec::new();
+                for child in database.get_children(id).await?.into_iter() {
+                    children.push(database.get_node(child).await?);
+                }
+                // Whatever's beneath a closed node is closed along with it.
+                let under_closed = path.iter().any(|ancestor| !ancestor.is_open());
+                for category in categories(&node, &children, now).into_iter() {
+                    let skip = match category {
+                        Category::Completed => false,
+                        _ => under_closed,
+                    };
+                    if !skip {
+                        items.push(ReviewItem {
+                            category,
+                            node: node.clone(),
+                            path: path.clone(),
+                            reviewed: reviewed.get(&id).copied(),
+                        });
+                    }
+                }
+            }
+            path.push(node);
+        }
+    }
+
+    // Sorting is stable, so everything else stays in the order it was walked.
+    items.sort_by(|a, b| {
+        a.category.cmp(&b.category).then_with(|| match a.category {
+            Category::Stale => a.reviewed.cmp(&b.reviewed),
+            Category::Overdue => a.node.due.cmp(&b.node.due),
+            Category::Completed => b.node.completed.cmp(&a.node.completed),
+            Category::Undated => std::cmp::Ordering::Equal,
+        })
+    });
+    Ok(items)
+}
+
+/// Which categories `node` falls into, on its own merits.
+fn categories(node: &db::Node, children: &[db::Node], now: DateTime<Utc>) -> Vec<Category> {
+    if node.trashed {
+        return vec![];
+    }
+    if let Some(completed) = node.completed {
+        if completed > now - Duration::days(REVIEW_PERIOD_DAYS) {
+            return vec![Category::Completed];
+        }
+        return vec![];
+    }
+
+    let mut categories = Vec::new();
+    if !children.is_empty() && children.iter().all(|child| !child.is_open()) {
+        categories.push(Category::Stale);
+    }
+    match node.due {
+        Some(due) if due < now => categories.push(Category::Overdue),
+        None if children.is_empty() => categories.push(Category::Undated),
+        _ => {}
+    }
+    categories
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    fn node(title: &str) -> db::Node {
+        let mut node = db::Node::new();
+        node.title = title.to_string();
+        node
+    }
+
+    #[test]
+    fn test_categories__stale_project() {
+        let now = Utc::now();
+        let mut done = node("done");
+        done.completed = Some(now - Duration::days(30));
+        let mut project = node("project");
+        project.due = Some(now + Duration::days(1));
+
+        assert_eq!(
+            categories(&project, &[done.clone()], now),
+            vec![Category::Stale]
+        );
+        assert_eq!(
+            categories(&project, &[done, node("open")], now),
+            Vec::<Category>::new()
+        );
+    }
+
+    #[test]
+    fn test_categories__leaves() {
+        let now = Utc::now();
+        let mut overdue = node("overdue");
+        overdue.due = Some(now - Duration::hours(1));
+
+        assert_eq!(categories(&node("leaf"), &[], now), vec![Category::Undated]);
+        assert_eq!(categories(&overdue, &[], now), vec![Category::Overdue]);
+    }
+
+    #[test]
+    fn test_categories__completed_this_week() {
+        let now = Utc::now();
+        let mut recent = node("recent");
+        recent.completed = Some(now - Duration::days(2));
+        let mut old = node("old");
+        old.completed = Some(now - Duration::days(8));
+
+        assert_eq!(categories(&recent, &[], now), vec![Category::Completed]);
+        assert_eq!(categories(&old, &[], now), Vec::<Category>::new());
+    }
+}
@@ -1,7 +1,9 @@
 INSERT INTO edges (
        from_uuid,
-       to_uuid
+       to_uuid,
+       kind
 ) VALUES (
+       ?,
        ?,
        ?
 )
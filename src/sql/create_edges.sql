@@ -1,6 +1,7 @@
 CREATE TABLE edges (
        from_uuid TEXT,
        to_uuid TEXT,
+       kind TEXT NOT NULL DEFAULT 'contains',
 
-       PRIMARY KEY(from_uuid, to_uuid)
+       PRIMARY KEY(from_uuid, to_uuid, kind)
 );
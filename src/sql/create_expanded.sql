@@ -0,0 +1,3 @@
+CREATE TABLE IF NOT EXISTS expanded (
+       uuid TEXT PRIMARY KEY
+);
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS inbox (
+       id INTEGER PRIMARY KEY CHECK (id = 0),
+       uuid TEXT NOT NULL
+);
@@ -5,5 +5,6 @@ CREATE TABLE nodes (
        scheduled REAL,
        due REAL,
        completed REAL,
-       trashed BIT
+       trashed BIT,
+       estimate INTEGER
 );
@@ -0,0 +1,4 @@
+CREATE TABLE IF NOT EXISTS reviewed (
+       uuid TEXT PRIMARY KEY,
+       reviewed REAL NOT NULL
+);
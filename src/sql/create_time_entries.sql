@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS time_entries (
+       id INTEGER PRIMARY KEY,
+       uuid TEXT NOT NULL,
+       started REAL NOT NULL,
+       stopped REAL
+);
@@ -3,3 +3,4 @@ FROM edges
 INNER JOIN nodes
   ON edges.to_uuid = nodes.uuid
 WHERE edges.from_uuid = ?
+  AND edges.kind = ?
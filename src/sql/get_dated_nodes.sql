@@ -0,0 +1,4 @@
+SELECT uuid
+FROM nodes
+WHERE (scheduled IS NOT NULL OR due IS NOT NULL)
+  AND trashed = 0
@@ -0,0 +1,3 @@
+SELECT uuid
+FROM nodes
+WHERE uuid LIKE ? || '%'
@@ -0,0 +1,6 @@
+SELECT nodes.uuid
+FROM edges
+INNER JOIN nodes
+  ON edges.from_uuid = nodes.uuid
+WHERE edges.to_uuid = ?
+  AND edges.kind = ?
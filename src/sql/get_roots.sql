@@ -2,5 +2,7 @@ SELECT nodes.uuid
 FROM nodes
 LEFT JOIN edges
   ON nodes.uuid = edges.to_uuid
+  AND edges.kind = 'contains'
 WHERE nodes.uuid IS NOT NULL
   AND edges.to_uuid IS NULL
+  AND nodes.uuid NOT IN (SELECT uuid FROM inbox)
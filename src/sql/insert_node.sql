@@ -5,7 +5,8 @@ INSERT INTO nodes (
        scheduled,
        due,
        completed,
-       trashed
+       trashed,
+       estimate
 ) VALUES (
        ?,
        ?,
@@ -13,5 +14,6 @@ INSERT INTO nodes (
        ?,
        ?,
        ?,
+       ?,
        ?
 )
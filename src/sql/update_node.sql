@@ -4,5 +4,6 @@ SET title = ?,
     scheduled = ?,
     due = ?,
     completed = ?,
-    trashed = ?
+    trashed = ?,
+    estimate = ?
 WHERE uuid = ?
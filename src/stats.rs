@@ -0,0 +1,92 @@
+use std::collections::BTreeSet;
+
+use structopt::StructOpt;
+
+use crate::db;
+use crate::node_ref::NodeRef;
+use crate::output;
+
+#[derive(Debug, StructOpt)]
+pub struct Args {
+    /// Only show this node, rather than every area and project.
+    node: Option<NodeRef>,
+    /// Only show projects with nothing left to do beneath them.
+    #[structopt(long = "stalled")]
+    stalled: bool,
+}
+
+pub async fn main(
+    args: Args,
+    database: db::Database,
+    output: &mut output::Output,
+    exact: bool,
+) -> anyhow::Result<()> {
+    if let Some(node) = args.node {
+        let id = node.resolve(&database, exact).await?;
+        let record = output::Record::new(database.get_node(id).await?)
+            .with_kind(database.kind(id).await?)
+            .with_stats(database.stats(id).await?);
+        return output.emit(record, |record| {
+            format!("{} {}", record.node.short_repr(), describe(record))
+        });
+    }
+
+    let mut seen = BTreeSet::new();
+    for root in database.get_roots().await?.into_iter() {
+        let mut path: Vec<db::Node> = Vec::new();
+        let mut dfs = database.dfs(root).await?;
+        while let Some((id, depth)) = dfs.next().await? {
+            path.truncate(depth);
+            let node = database.get_node(id).await?;
+            let kind = database.kind(id).await?;
+            // Tasks have nothing beneath them to summarize.
+            if seen.insert(id) && kind != db::Kind::Task {
+                let stats = database.stats(id).await?;
+                if !args.stalled || stats.stalled {
+                    let record = output::Record::new(node.clone())
+                        .with_path(&path)
+                        .with_kind(kind)
+                        .with_stats(stats);
+                    output.emit(record, |record| {
+                        let mut titles: Vec<&str> =
+                            path.iter().map(|node| node.title.as_str()).collect();
+                        titles.push(&record.node.title);
+                        format!(
+                            "{} ({}) {}",
+                            titles.join(" / "),
+                            record.node.id,
+                            describe(record)
+                        )
+                    })?;
+                }
+            }
+            path.push(node);
+        }
+    }
+    Ok(())
+}
+
+/// A short summary of a record's kind and stats, for text output.
+pub fn describe(record: &output::Record) -> String {
+    let Some(kind) = record.kind else { return String::new() };
+    // Tasks and orphans have nothing beneath them, so there's nothing to summarize.
+    let (db::Kind::Area | db::Kind::Project, Some(stats)) = (kind, &record.stats) else {
+        return format!("[{}]", kind.name());
+    };
+
+    let mut parts = Vec::new();
+    if let Some(completion) = stats.completion() {
+        parts.push(format!(
+            "{}/{} done ({}%)",
+            stats.completed, stats.total, completion
+        ));
+    }
+    parts.push(format!("{} open", stats.open_leaves));
+    if let Some(next_due) = stats.next_due {
+        parts.push(format!("next due {}", next_due.format("%Y-%m-%d")));
+    }
+    if stats.stalled {
+        parts.push("stalled".to_string());
+    }
+    format!("[{}: {}]", kind.name(), parts.join(", "))
+}
@@ -0,0 +1,98 @@
+use anyhow::anyhow;
+use chrono::Duration;
+use chrono::Utc;
+use structopt::StructOpt;
+
+use crate::db;
+use crate::node_ref::NodeRef;
+use crate::output;
+
+#[derive(Debug, StructOpt)]
+pub struct StartArgs {
+    node: NodeRef,
+}
+
+pub async fn start(
+    args: StartArgs,
+    database: db::Database,
+    output: &mut output::Output,
+    exact: bool,
+) -> anyhow::Result<()> {
+    let id = args.node.resolve(&database, exact).await?;
+    let node = database.get_node(id).await?;
+    // Starting the timer that's already running would just split its entry in two.
+    if let Some(running) = database.get_running_timer().await? {
+        if running.node == id {
+            let elapsed = Utc::now() - running.started;
+            let record = output::Record::new(node).with_tracked(elapsed);
+            return output.emit(record, |record| {
+                format!(
+                    "Already timing {} for {}",
+                    record.node.short_repr(),
+                    format_elapsed(elapsed)
+                )
+            });
+        }
+    }
+
+    let stopped = database.start_timer(id, Utc::now()).await?;
+    let stopped_text = match &stopped {
+        Some(entry) => {
+            let stopped_node = database.get_node(entry.node).await?;
+            format!(
+                "Stopped {} after {}\n",
+                stopped_node.short_repr(),
+                format_elapsed(entry.duration_between(None, Utc::now()))
+            )
+        }
+        None => String::new(),
+    };
+    let record = output::Record::new(node).with_tracked(Duration::zero());
+    output.emit(record, |record| {
+        format!("{}Started {}", stopped_text, record.node.short_repr())
+    })
+}
+
+pub async fn stop(database: db::Database, output: &mut output::Output) -> anyhow::Result<()> {
+    let Some(entry) = database.stop_timer(Utc::now()).await? else {
+        return Err(anyhow!("No timer is running"));
+    };
+    let elapsed = entry.duration_between(None, Utc::now());
+    let record = output::Record::new(database.get_node(entry.node).await?).with_tracked(elapsed);
+    output.emit(record, |record| {
+        format!(
+            "Stopped {} after {}",
+            record.node.short_repr(),
+            format_elapsed(elapsed)
+        )
+    })
+}
+
+pub async fn status(database: db::Database, output: &mut output::Output) -> anyhow::Result<()> {
+    let Some(entry) = database.get_running_timer().await? else {
+        // Machine-readable output is just left empty, so this only goes to stderr.
+        eprintln!("No timer is running");
+        return Ok(());
+    };
+    let elapsed = Utc::now() - entry.started;
+    let record = output::Record::new(database.get_node(entry.node).await?).with_tracked(elapsed);
+    output.emit(record, |record| {
+        format!(
+            "Timing {} for {} (since {})",
+            record.node.short_repr(),
+            format_elapsed(elapsed),
+            output::format_local(entry.started)
+        )
+    })
+}
+
+/// Writes a duration like a stopwatch would, as `h:mm:ss`.
+pub fn format_elapsed(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
@@ -1,25 +1,61 @@
+use std::collections::BTreeSet;
+use std::io::Stdout;
+use std::panic::PanicHookInfo;
+use std::sync::Arc;
 use std::time::Duration;
+use std::time::Instant;
 
+use chrono::DateTime;
+use chrono::Utc;
+use chrono::Weekday;
+use crossterm::cursor;
 use crossterm::event;
+use crossterm::event::DisableBracketedPaste;
+use crossterm::event::DisableMouseCapture;
+use crossterm::event::EnableBracketedPaste;
+use crossterm::event::EnableMouseCapture;
 use crossterm::event::Event;
 use crossterm::event::KeyCode;
 use crossterm::event::KeyEvent;
 use crossterm::event::KeyModifiers;
+use crossterm::event::MouseButton;
+use crossterm::event::MouseEvent;
+use crossterm::event::MouseEventKind;
 use crossterm::terminal::disable_raw_mode;
 use crossterm::terminal::enable_raw_mode;
+use crossterm::terminal::EnterAlternateScreen;
+use crossterm::terminal::LeaveAlternateScreen;
 use ratatui::backend::Backend;
 use ratatui::backend::CrosstermBackend;
 use ratatui::layout;
 use ratatui::layout::Constraint;
 use ratatui::layout::Direction;
 use ratatui::layout::Layout;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
 use ratatui::widgets as rwidgets;
 use ratatui::Frame;
 use ratatui::Terminal;
+use unicode_width::UnicodeWidthStr;
 
+use crate::config;
 use crate::db;
+use crate::edit;
 use crate::find::find_candidates;
-
+use crate::keys;
+use crate::timer;
+
+mod agenda;
+mod bulk;
+mod connect;
+mod inbox;
+pub mod keymap;
+mod next;
+mod parents;
+mod review;
 mod util;
 mod widgets;
 
@@ -34,34 +70,88 @@ mod widgets;
 //     - block on the right w/ the current contents
 //       - title = name of the node
 //       - body = paragraph
-//     - cycle between node list / title / body to interact with each
-//     - keybinds
+//     - cycle between node list / title / dates / body to interact with each
+//     - completed and trashed nodes are struck through, overdue ones are red
+//     - areas, projects and orphans get a badge with how much beneath them is done,
+//       and projects with nothing left to do are marked as stalled
+//     - blocked nodes list what they're waiting on, and b = pick a node to block them
+//       (tab swaps to making them block it instead)
+//     - edits save on tab, before acting on the list, after a pause in typing, and on exit
+//       - if saving fails, a prompt offers to retry or discard rather than losing them silently
+//       - if someone else saved the node first, a prompt offers to overwrite, merge or take theirs
+//     - reloads when something else writes to the database, unless there are unsaved edits
+//     - keybinds (defaults; see keymap.rs, and `?` or F1 lists them in the UI)
 //       - tab -> cycle
 //       - a -> add
+//       - x -> toggle completion
+//       - d -> toggle trash
+//       - t -> toggle between the list and tree views
 //       - f -> find
 //       - c -> connect
 //       - n -> next
+//       - p -> pick any parent of the selected node to jump to
+//       - s -> agenda
+//       - i -> inbox
+//       - R -> weekly review
+//     - marking
+//       - space = mark / unmark the selected row, v = start / finish marking a range
+//       - esc = unmark everything, and marks are dropped when moving to another node
+//       - x, d, c act on everything marked, as do m (move), T (tag) and D (due date)
+//       - each bulk action is one transaction, so it either all happens or none of it does
+//     - a breadcrumb along the top shows the path to the current node
+//       - with the running timer from `tasq start` on the right, ticking each second
+//     - e = edit the selected node as JSON in $EDITOR, same as `tasq edit`
+//     - Ctrl+Z = suspend to the shell, like any other program
+//     - mouse
+//       - click = select a row or focus a field, double click = view the row's children
+//       - wheel = move through the list
+//       - drag the border between the list and the editor to resize them
+//     - tree view
+//       - right = expand, or step into an expanded node
+//       - left = collapse, or step out to the parent row
+//       - enter = view the selected node's children
+//       - expansion is saved in the database, so it survives restarts
 //     - for each sub mode: render normal mode behind them
+//       - lists in sub modes take clicks and the wheel, double click = choose
 //   - add
-//     - tab = swap between title / description
+//     - tab = cycle between title / scheduled / due / description
 //     - Ctrl+F = finalize
-//     - TODO: set things like scheduled and due dates
 //   - find
 //     - up = select up
 //     - down = select down
 //     - Ctrl+F = choose currently selected node
 //     - everything else = normal text editing!
 //   - connect
-//     - TODO
+//     - reuses the find picker to choose a target
+//     - tab = swap between making the selected node a child / parent of the target
+//     - Ctrl+F = connect, unless it would create a cycle
+//     - also moves nodes, swapping the parent they're listed under for the target
+//   - inbox
+//     - steps through the inbox's open, unscheduled children: what `tasq capture` adds
+//     - x = do it (complete), m = move under a project, s = schedule, d = trash
+//     - up / down = go back to / skip an item
+//     - moving and scheduling reuse the find and prompt keys
+//   - review
+//     - stale projects, overdue nodes, undated leaves, and what was completed this week
+//     - x = toggle completion, d = toggle trash, r = mark as reviewed, enter = jump to the node
+//     - acted-on nodes stay listed (struck through) until the review is reopened
+//   - prompt
+//     - one line of text for a bulk action: a tag's title, or a due date
+//     - tags are nodes, made as a new root if no node has that title
 //   - next
-//     - TODO
+//     - lists the open leaves under the current node, with their paths
+//     - x = complete the selected node
+//     - enter = jump to the selected node
+//   - agenda
+//     - open nodes with a date, grouped into overdue / today / tomorrow / this week / later
+//     - a calendar of the selected node's month, with how many nodes land on each day
+//     - + / - = move the selected node's date a day later / earlier
+//     - enter = jump to the selected node
 
 // TODO: turn these into shared widgets?
 // noticing some common components:
 //
-// - text editing
-//   - single-line text editor
-//   - multi-line (paragraph) text editor
+// - text editing (done: see widgets::TextArea)
 // - modal dialog
 //   - a dialog on top of the parent screen
 //   - renders "on top of" the parent
@@ -69,70 +159,263 @@ mod widgets;
 //   - top-level find going to a node
 //   - finding another node to connect to
 
-pub async fn main(database: db::Database) -> anyhow::Result<()> {
-    // Magic spell to clear the screen with an ANSI escape code.
-    print!("{}[2J]", 27 as char);
-
-    let stdout = std::io::stdout();
-    let _raw_mode = RawModeGuard::new()?;
+pub async fn main(database: db::Database, config: config::Config) -> anyhow::Result<()> {
+    let keymap = keymap::Keymap::new(&config.keys);
+    let editor = config.editor_command()?;
+    let theme = config.theme;
 
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut session = TerminalSession::new()?;
 
-    let mut mode = Mode::Normal(NormalState::new(&database, None).await?);
+    let mut mode = Mode::Normal(NormalState::new(&database, None, config.week_start).await?);
+    let mut show_help = false;
+    let mut clicks = ClickTracker::default();
     loop {
-        terminal.draw(|f| {
-            mode.render(f);
+        if let Mode::Normal(state) = &mut mode {
+            state.autosave(&database).await;
+            state.reload_if_changed(&database).await?;
+        }
+        session.terminal.draw(|f| {
+            mode.render(f, &theme);
+            if show_help {
+                render_help(f, &keymap, mode.key_context(), &theme);
+            }
         })?;
+        // Stats are filled in for the rows that were just drawn, so draw again if any were missing.
+        if let Mode::Normal(state) = &mut mode {
+            if state.load_visible_stats(&database).await? {
+                continue;
+            }
+        }
 
         if !event::poll(Duration::from_millis(1000))? {
             continue;
         }
         let evt = match event::read()? {
             Event::Key(evt) => evt,
+            Event::Paste(text) => {
+                mode = mode.handle_paste(&database, text).await?;
+                continue;
+            }
+            Event::Mouse(evt) => {
+                let double_click = clicks.is_double_click(&evt);
+                if show_help {
+                    // Clicking anywhere dismisses help, same as any key.
+                    if let MouseEventKind::Down(_) = evt.kind {
+                        show_help = false;
+                    }
+                    continue;
+                }
+                mode = mode.handle_mouse(&database, evt, double_click).await?;
+                continue;
+            }
+            Event::Resize(_, _) => {
+                session.terminal.autoresize()?;
+                continue;
+            }
             _ => continue,
         };
 
-        if evt.code == KeyCode::Char('c') && evt.modifiers.contains(KeyModifiers::CONTROL) {
-            break;
+        if show_help {
+            // Any key dismisses help.
+            show_help = false;
+            continue;
+        }
+
+        let action = keymap.action(mode.key_context(), evt);
+        if action == Some(keys::Action::Help) {
+            show_help = true;
+            continue;
+        }
+        if action == Some(keys::Action::Quit) {
+            match mode.quit(&database).await? {
+                None => break,
+                Some(next) => mode = next,
+            }
+            continue;
+        }
+        if action == Some(keys::Action::Suspend) {
+            session.suspend()?;
+            continue;
+        }
+        if action == Some(keys::Action::Edit) {
+            if let Mode::Normal(state) = &mut mode {
+                state.edit_selected(&database, &editor, &mut session).await?;
+            }
+            continue;
         }
 
-        mode = mode.handle_input(&database, evt).await?;
+        mode = mode.handle_input(&database, &keymap, evt).await?;
+        if let Mode::Normal(NormalState { quit: true, .. }) = mode {
+            break;
+        }
     }
 
     Ok(())
 }
 
+// Every mode carries a `NormalState` so they're all large,
+// and there's only ever one of them, so boxing buys nothing.
+#[allow(clippy::large_enum_variant)]
 enum Mode {
     Normal(NormalState),
     Add(AddState),
     Find(FindState),
+    Connect(connect::ConnectState),
+    Next(next::NextState),
+    Parents(parents::ParentsState),
+    Agenda(agenda::AgendaState),
+    Prompt(bulk::PromptState),
+    Inbox(inbox::InboxState),
+    Review(review::ReviewState),
 }
 
 impl Mode {
-    async fn handle_input(self, database: &db::Database, evt: KeyEvent) -> anyhow::Result<Mode> {
+    async fn handle_input(
+        self,
+        database: &db::Database,
+        keymap: &keymap::Keymap,
+        evt: KeyEvent,
+    ) -> anyhow::Result<Mode> {
         use Mode::*;
+        let action = keymap.action(self.key_context(), evt);
         match self {
-            Normal(state) => state.handle_input(database, evt).await,
-            Add(state) => state.handle_input(database, evt).await,
-            Find(state) => state.handle_input(database, evt).await,
+            Normal(state) => state.handle_input(database, action, evt).await,
+            Add(state) => state.handle_input(database, action, evt).await,
+            Find(state) => state.handle_input(database, action, evt).await,
+            Connect(state) => state.handle_input(database, action, evt).await,
+            Next(state) => state.handle_input(database, action).await,
+            Parents(state) => state.handle_input(database, action).await,
+            Agenda(state) => state.handle_input(database, action).await,
+            Prompt(state) => state.handle_input(database, action, evt).await,
+            Inbox(state) => state.handle_input(database, action, evt).await,
+            Review(state) => state.handle_input(database, action).await,
         }
     }
 
-    fn render(&mut self, f: &mut Frame<impl Backend>) {
+    async fn handle_mouse(
+        self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        use Mode::*;
+        match self {
+            Normal(state) => state.handle_mouse(database, evt, double_click).await,
+            Add(state) => Ok(state.handle_mouse(evt)),
+            Find(state) => state.handle_mouse(database, evt, double_click).await,
+            Connect(state) => state.handle_mouse(database, evt, double_click).await,
+            Next(state) => state.handle_mouse(database, evt, double_click).await,
+            Parents(state) => state.handle_mouse(database, evt, double_click).await,
+            Agenda(state) => state.handle_mouse(database, evt, double_click).await,
+            Prompt(state) => Ok(Prompt(state)),
+            Inbox(state) => state.handle_mouse(database, evt, double_click).await,
+            Review(state) => state.handle_mouse(database, evt, double_click).await,
+        }
+    }
+
+    /// Which keybindings apply right now.
+    fn key_context(&self) -> keymap::Context {
+        use Mode::*;
+        match self {
+            Normal(state) => match state.mode {
+                NormalStateMode::List => keymap::Context::List,
+                _ => keymap::Context::Editor,
+            },
+            Add(_) => keymap::Context::Add,
+            Find(_) => keymap::Context::Find,
+            Connect(_) => keymap::Context::Connect,
+            Next(_) => keymap::Context::Next,
+            Parents(_) => keymap::Context::Parents,
+            Agenda(_) => keymap::Context::Agenda,
+            Prompt(_) => keymap::Context::Prompt,
+            Inbox(state) => state.key_context(),
+            Review(_) => keymap::Context::Review,
+        }
+    }
+
+    async fn handle_paste(self, database: &db::Database, text: String) -> anyhow::Result<Mode> {
+        use Mode::*;
+        match self {
+            Normal(state) => Ok(state.handle_paste(text)),
+            Add(state) => Ok(state.handle_paste(text)),
+            Find(state) => state.handle_paste(database, text).await,
+            Connect(state) => state.handle_paste(database, text).await,
+            Next(state) => Ok(Next(state)),
+            Parents(state) => Ok(Parents(state)),
+            Agenda(state) => Ok(Agenda(state)),
+            Prompt(state) => Ok(state.handle_paste(text)),
+            Inbox(state) => state.handle_paste(database, text).await,
+            Review(state) => Ok(Review(state)),
+        }
+    }
+
+    /// Drops whatever dialog is open, going back to the normal mode underneath it.
+    /// Saves whatever's in progress so that it's safe to quit.
+    /// If that fails, returns the mode to carry on in, with the user asked whether to quit anyway.
+    async fn quit(self, database: &db::Database) -> anyhow::Result<Option<Mode>> {
+        let mut state = match self {
+            // A node still being added isn't in the database yet, so it'd be lost otherwise.
+            Mode::Add(mut state) => {
+                let editor = &state.node_editor_state;
+                if editor.node() != editor.original() {
+                    if let Err(e) = state.add(database).await {
+                        // Keep the dialog up so that the node isn't lost along with the UI.
+                        state.save_prompt = Some(SavePrompt {
+                            problem: SaveProblem::Failed(e.to_string()),
+                            quitting: true,
+                        });
+                        return Ok(Some(Mode::Add(state)));
+                    }
+                }
+                state.parent
+            }
+            mode => mode.into_normal(),
+        };
+        if state.try_quit(database).await {
+            return Ok(None);
+        }
+        Ok(Some(Mode::Normal(state)))
+    }
+
+    fn into_normal(self) -> NormalState {
         use Mode::*;
         match self {
-            Normal(state) => state.render(f),
-            Add(state) => state.render(f),
-            Find(state) => state.render(f),
+            Normal(state) => state,
+            Add(state) => state.parent,
+            Find(state) => state.parent,
+            Connect(state) => state.into_parent(),
+            Next(state) => state.into_parent(),
+            Parents(state) => state.into_parent(),
+            Agenda(state) => state.into_parent(),
+            Prompt(state) => state.into_parent(),
+            Inbox(state) => state.into_parent(),
+            Review(state) => state.into_parent(),
+        }
+    }
+
+    fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        use Mode::*;
+        match self {
+            Normal(state) => state.render(f, theme),
+            Add(state) => state.render(f, theme),
+            Find(state) => state.render(f, theme),
+            Connect(state) => state.render(f, theme),
+            Next(state) => state.render(f, theme),
+            Parents(state) => state.render(f, theme),
+            Agenda(state) => state.render(f, theme),
+            Prompt(state) => state.render(f, theme),
+            Inbox(state) => state.render(f, theme),
+            Review(state) => state.render(f, theme),
         }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 enum NormalStateMode {
     List,
     Title,
+    Scheduled,
+    Due,
     Description,
 }
 
@@ -141,7 +424,9 @@ impl NormalStateMode {
         use NormalStateMode::*;
         match self {
             List => Title,
-            Title => Description,
+            Title => Scheduled,
+            Scheduled => Due,
+            Due => Description,
             Description => List,
         }
     }
@@ -151,150 +436,849 @@ impl NormalStateMode {
         match self {
             List => Description,
             Title => List,
-            Description => Title,
+            Scheduled => Title,
+            Due => Scheduled,
+            Description => Due,
+        }
+    }
+}
+
+impl From<widgets::NodeEditorMode> for NormalStateMode {
+    fn from(mode: widgets::NodeEditorMode) -> Self {
+        use widgets::NodeEditorMode::*;
+        match mode {
+            Title => NormalStateMode::Title,
+            Scheduled => NormalStateMode::Scheduled,
+            Due => NormalStateMode::Due,
+            Description => NormalStateMode::Description,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum NormalStateView {
+    /// One level of children at a time.
+    List,
+    /// Children along with the subtrees of any expanded nodes.
+    Tree,
+}
+
+/// How a row of the node list sits in the tree, alongside the node it shows.
+struct TreeRow {
+    depth: usize,
+    child_count: usize,
+    parent_count: usize,
+    expanded: bool,
+    /// Only worked out for nodes with children, since there's nothing to summarize otherwise,
+    /// and only once the row is on screen; see [`NormalState::load_visible_stats`].
+    stats: Option<db::Stats>,
+    /// The open nodes which have to be done before this one can be started.
+    blockers: Vec<db::Node>,
+}
+
+impl TreeRow {
+    /// A summary of what kind of node this is and how far along it is.
+    /// Tasks are what most rows are, so they go without.
+    fn badge(&self) -> Vec<Span<'static>> {
+        let dim = Style::default().fg(Color::DarkGray);
+        let kind = db::Kind::of(self.parent_count, self.child_count);
+        if kind == db::Kind::Task {
+            return vec![];
+        }
+        let text = match self.stats.as_ref().and_then(db::Stats::completion) {
+            Some(completion) => format!(" {} {}%", kind.name(), completion),
+            None => format!(" {}", kind.name()),
+        };
+        let mut spans = vec![Span::styled(text, dim)];
+        if self.stats.as_ref().is_some_and(|stats| stats.stalled) {
+            spans.push(Span::styled(" stalled", Style::default().fg(Color::Yellow)));
         }
+        spans
+    }
+
+    fn blocked_by(&self) -> Option<Span<'static>> {
+        if self.blockers.is_empty() {
+            return None;
+        }
+        let titles: Vec<&str> = self
+            .blockers
+            .iter()
+            .map(|node| node.title.as_str())
+            .collect();
+        Some(Span::styled(
+            format!(" blocked by {}", titles.join(", ")),
+            Style::default().fg(Color::Magenta),
+        ))
+    }
+}
+
+/// How long typing has to pause before edits are saved on their own.
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
+
+/// How close together two clicks have to be to count as a double click.
+const DOUBLE_CLICK_DELAY: Duration = Duration::from_millis(500);
+
+/// How much of the screen the node list starts off taking up, as a percentage.
+const DEFAULT_LIST_WIDTH: u16 = 30;
+/// The narrowest either side of the divider can be dragged, as a percentage.
+const MIN_LIST_WIDTH: u16 = 10;
+
+enum SaveProblem {
+    /// The database refused the write.
+    Failed(String),
+    /// Someone else saved the node after we loaded it. This holds their version.
+    Conflict(db::Node),
+}
+
+/// Shown when the node being edited couldn't be saved.
+struct SavePrompt {
+    problem: SaveProblem,
+    /// Whether this came up while trying to quit.
+    quitting: bool,
+}
+
+impl SavePrompt {
+    fn render(&self, f: &mut Frame<impl Backend>) {
+        let size = f.size();
+        let width = (size.width * 3 / 5).max(40).min(size.width);
+        let height = 7.min(size.height);
+        let rect = layout::Rect {
+            x: (size.width - width) / 2,
+            y: (size.height - height) / 2,
+            width,
+            height,
+        };
+        f.render_widget(rwidgets::Clear, rect);
+
+        let (title, message, options) = match &self.problem {
+            SaveProblem::Failed(error) => (
+                "Couldn't save changes",
+                error.clone(),
+                if self.quitting {
+                    "r: retry, q: quit without saving, Esc: keep editing"
+                } else {
+                    "r: retry, d: discard changes, Esc: keep editing"
+                },
+            ),
+            SaveProblem::Conflict(theirs) => (
+                "Edit conflict",
+                format!("\"{}\" was changed elsewhere since you started editing it.", theirs.title),
+                "o: overwrite, m: merge, t: take theirs, Esc: keep editing",
+            ),
+        };
+        let text = vec![
+            Spans::from(message),
+            Spans::from(""),
+            Spans::from(Span::styled(options, Style::default().fg(Color::DarkGray))),
+        ];
+        let paragraph = rwidgets::Paragraph::new(text)
+            .wrap(rwidgets::Wrap { trim: true })
+            .block(
+                rwidgets::Block::default()
+                    .title(title)
+                    .borders(rwidgets::Borders::all())
+                    .border_style(Style::default().fg(Color::Red)),
+            );
+        f.render_widget(paragraph, rect);
     }
 }
 
-// TODO: migrate over NormalState to use NodeEditor widget
 struct NormalState {
     mode: NormalStateMode,
+    view: NormalStateView,
+    /// The node whose children are being listed, or `None` when listing the roots.
+    node: Option<db::Node>,
     node_path: Vec<db::Node>,
+    /// Every row in the node list.
+    /// In the tree view this includes the descendants of expanded nodes.
     children: Vec<db::Node>,
+    rows: Vec<TreeRow>,
+    expanded: BTreeSet<db::NodeID>,
     node_list_state: rwidgets::ListState,
     node_editor_state: widgets::NodeEditorState,
+    save_prompt: Option<SavePrompt>,
+    /// Set when the user dismisses a failed save,
+    /// so that autosave doesn't keep failing at them until they edit again.
+    autosave_paused: bool,
+    /// Set once the user has chosen to quit without saving.
+    quit: bool,
+    /// The database's `data_version` as of the last reload.
+    data_version: i64,
+    /// Something which went wrong, shown in place of the breadcrumb until the next key press.
+    message: Option<String>,
+    /// The node `tasq start` is timing and when it started, if a timer is running.
+    timer: Option<(db::Node, DateTime<Utc>)>,
+    /// Which day weeks start on in the agenda.
+    week_start: Weekday,
+    /// How much of the screen the node list takes up, as a percentage.
+    list_width: u16,
+    /// Set while the divider between the list and the editor is being dragged.
+    resizing: bool,
+    /// Where things were last drawn, for working out what the mouse is pointing at.
+    body_area: layout::Rect,
+    editor_area: layout::Rect,
+    list_hitbox: widgets::ListHitbox,
+    /// Rows picked out for a bulk action.
+    marked: BTreeSet<db::NodeID>,
+    /// Where a range being marked started, while one is.
+    visual_anchor: Option<usize>,
 }
 
 impl NormalState {
-    async fn new(database: &db::Database, root: Option<db::Node>) -> anyhow::Result<Self> {
+    async fn new(
+        database: &db::Database,
+        root: Option<db::Node>,
+        week_start: Weekday,
+    ) -> anyhow::Result<Self> {
         let mut state = Self {
             mode: NormalStateMode::List,
+            view: NormalStateView::List,
+            node: root,
             node_path: vec![],
             children: vec![],
+            rows: vec![],
+            expanded: database.get_expanded().await?,
             node_list_state: rwidgets::ListState::default(),
-            node_editor_state: widgets::NodeEditorState::new(root),
+            node_editor_state: widgets::NodeEditorState::new(None),
+            save_prompt: None,
+            autosave_paused: false,
+            quit: false,
+            data_version: database.data_version().await?,
+            message: None,
+            timer: None,
+            week_start,
+            list_width: DEFAULT_LIST_WIDTH,
+            resizing: false,
+            body_area: layout::Rect::default(),
+            editor_area: layout::Rect::default(),
+            list_hitbox: widgets::ListHitbox::default(),
+            marked: BTreeSet::new(),
+            visual_anchor: None,
         };
         state.refresh(database).await?;
+        state.load_timer(database).await?;
         Ok(state)
     }
 
     async fn handle_input(
         mut self,
         database: &db::Database,
+        action: Option<keys::Action>,
         evt: KeyEvent,
     ) -> anyhow::Result<Mode> {
-        // TODO: make this also persist state when you exit the program
-        if evt.code == KeyCode::BackTab {
-            self.mode = self.mode.last();
-            self.persist_changes(database).await?;
+        self.message = None;
+        // The prompt's choices are spelled out on screen, so they aren't remappable.
+        if self.save_prompt.is_some() {
+            return self.handle_save_prompt_input(database, evt).await;
+        }
+
+        if action == Some(keys::Action::PrevField) {
+            if self.save(database).await {
+                self.set_mode(self.mode.last());
+            }
             return Ok(Mode::Normal(self));
         }
-        if evt.code == KeyCode::Tab {
-            self.mode = self.mode.next();
-            self.persist_changes(database).await?;
+        if action == Some(keys::Action::NextField) {
+            if self.save(database).await {
+                self.set_mode(self.mode.next());
+            }
             return Ok(Mode::Normal(self));
         }
 
         use NormalStateMode::*;
         if let List = &self.mode {
-            return self.handle_list_input(database, evt).await;
+            // Everything in the list can move away from the node being edited,
+            // so make sure nothing is left unsaved first.
+            if !self.save(database).await {
+                return Ok(Mode::Normal(self));
+            }
+            let Some(action) = action else { return Ok(Mode::Normal(self)) };
+            return self.handle_list_input(database, action).await;
         }
 
         let Some(selected) = self.node_list_state.selected() else {
-	    return Ok(Mode::Normal(self));
-	};
-        let node = &mut self.children[selected];
+            return Ok(Mode::Normal(self));
+        };
+        if self.node_editor_state.handle_input(evt) {
+            self.autosave_paused = false;
+            // Keep the list in sync with what's being typed.
+            if let Some(node) = self.node_editor_state.node() {
+                self.children[selected] = node.clone();
+            }
+        }
+
+        Ok(Mode::Normal(self))
+    }
+
+    async fn handle_save_prompt_input(
+        mut self,
+        database: &db::Database,
+        evt: KeyEvent,
+    ) -> anyhow::Result<Mode> {
+        let Some(prompt) = self.save_prompt.take() else {
+            return Ok(Mode::Normal(self));
+        };
+
+        let is_ctrl_g =
+            evt.modifiers.contains(KeyModifiers::CONTROL) && evt.code == KeyCode::Char('g');
+        if evt.code == KeyCode::Esc || is_ctrl_g {
+            self.autosave_paused = true;
+            return Ok(Mode::Normal(self));
+        }
 
-        match self.mode {
-            List => unreachable!("This should be handled above..."),
-            Title => util::handle_input_single_line(&mut node.title, evt),
-            Description => util::handle_input_multi_line(&mut node.description, evt),
+        let (Some(original), Some(ours)) = (
+            self.node_editor_state.original().cloned(),
+            self.node_editor_state.node().cloned(),
+        ) else {
+            return Ok(Mode::Normal(self));
+        };
+        match (&prompt.problem, evt.code) {
+            (SaveProblem::Failed(_), KeyCode::Char('r')) => {}
+            (SaveProblem::Failed(_), KeyCode::Char('q')) if prompt.quitting => {
+                self.quit = true;
+                return Ok(Mode::Normal(self));
+            }
+            (SaveProblem::Failed(_), KeyCode::Char('d')) if !prompt.quitting => {
+                self.discard_changes(database).await?;
+                return Ok(Mode::Normal(self));
+            }
+            (SaveProblem::Conflict(theirs), KeyCode::Char('o')) => {
+                self.rebase(theirs.clone(), ours);
+            }
+            (SaveProblem::Conflict(theirs), KeyCode::Char('m')) => {
+                let merged = db::Node::merge(&original, &ours, theirs);
+                self.rebase(theirs.clone(), merged);
+            }
+            (SaveProblem::Conflict(_), KeyCode::Char('t')) => {
+                self.discard_changes(database).await?;
+                self.quit = prompt.quitting;
+                return Ok(Mode::Normal(self));
+            }
+            _ => {
+                self.save_prompt = Some(prompt);
+                return Ok(Mode::Normal(self));
+            }
         }
 
+        // If this fails again it puts the prompt back up.
+        if self.save(database).await {
+            self.quit = prompt.quitting;
+        } else if let Some(new_prompt) = &mut self.save_prompt {
+            new_prompt.quitting = prompt.quitting;
+        }
         Ok(Mode::Normal(self))
     }
 
-    async fn persist_changes(&self, database: &db::Database) -> anyhow::Result<()> {
+    /// Puts `ours` in the editor as unsaved changes on top of `theirs`.
+    fn rebase(&mut self, theirs: db::Node, ours: db::Node) {
+        if let Some(selected) = self.node_list_state.selected() {
+            self.children[selected] = ours.clone();
+        }
+        self.node_editor_state.rebase(theirs, ours);
+    }
+
+    fn handle_paste(mut self, text: String) -> Mode {
+        if let NormalStateMode::List = self.mode {
+            return Mode::Normal(self);
+        }
         let Some(selected) = self.node_list_state.selected() else {
-	    return Ok(());
-	};
-        let node = &self.children[selected];
-        database.update(node).await
+            return Mode::Normal(self);
+        };
+        self.node_editor_state.paste(&text);
+        if let Some(node) = self.node_editor_state.node() {
+            self.children[selected] = node.clone();
+        }
+        Mode::Normal(self)
     }
 
-    async fn handle_list_input(
+    fn set_mode(&mut self, mode: NormalStateMode) {
+        self.mode = mode;
+        match mode {
+            NormalStateMode::List => {}
+            NormalStateMode::Title => self.node_editor_state.focus_title(),
+            NormalStateMode::Scheduled => self.node_editor_state.focus_scheduled(),
+            NormalStateMode::Due => self.node_editor_state.focus_due(),
+            NormalStateMode::Description => self.node_editor_state.focus_description(),
+        }
+    }
+
+    /// Saves the node being edited if it's changed, returning whether it's now safe to move on.
+    /// Failures are put in front of the user rather than tearing down the UI.
+    async fn save(&mut self, database: &db::Database) -> bool {
+        if !self.node_editor_state.is_dirty() {
+            return true;
+        }
+        let Some(node) = self.node_editor_state.node() else { return true };
+
+        // Missing nodes and the like are reported by the update itself.
+        if let Ok(theirs) = database.get_node(node.id).await {
+            if Some(&theirs) != self.node_editor_state.original() {
+                self.save_prompt = Some(SavePrompt {
+                    problem: SaveProblem::Conflict(theirs),
+                    quitting: false,
+                });
+                return false;
+            }
+        }
+
+        match database.update(node).await {
+            Ok(()) => {
+                self.node_editor_state.mark_saved();
+                true
+            }
+            Err(e) => {
+                self.save_prompt = Some(SavePrompt {
+                    problem: SaveProblem::Failed(e.to_string()),
+                    quitting: false,
+                });
+                false
+            }
+        }
+    }
+
+    /// Picks up changes made by anything else, like `tasq add` in another terminal.
+    /// This waits while there are unsaved edits, so that they don't get pulled out from under
+    /// the user; conflicts are caught when they're saved instead.
+    async fn reload_if_changed(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        if self.node_editor_state.is_dirty() || self.save_prompt.is_some() {
+            return Ok(());
+        }
+        let data_version = database.data_version().await?;
+        if data_version == self.data_version {
+            return Ok(());
+        }
+        self.data_version = data_version;
+        self.reload(database).await
+    }
+
+    async fn load_timer(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        self.timer = match database.get_running_timer().await? {
+            Some(entry) => Some((database.get_node(entry.node).await?, entry.started)),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Reloads everything shown, keeping the same row selected if it's still there.
+    async fn reload(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        self.load_timer(database).await?;
+        if let Some(node) = &self.node {
+            match database.get_node(node.id).await {
+                Ok(node) => self.node = Some(node),
+                // The node we were looking at is gone, so fall back to the roots.
+                Err(_) => {
+                    self.node = None;
+                    self.node_path.clear();
+                }
+            }
+        }
+        for ancestor in self.node_path.iter_mut() {
+            if let Ok(node) = database.get_node(ancestor.id).await {
+                *ancestor = node;
+            }
+        }
+
+        let selected = self.node_list_state.selected();
+        let selected_id = selected.map(|selected| self.children[selected].id);
+        self.load_children(database).await?;
+        let selected = selected_id
+            .and_then(|id| self.children.iter().position(|child| child.id == id))
+            .or(selected.map(|selected| selected.min(self.children.len().saturating_sub(1))))
+            .filter(|_| !self.children.is_empty());
+
+        // Leave the editor alone unless its node actually changed,
+        // so that the cursor doesn't jump around.
+        let unchanged = match (selected, self.node_editor_state.node()) {
+            (Some(selected), Some(node)) => &self.children[selected] == node,
+            (None, None) => true,
+            _ => false,
+        };
+        if unchanged {
+            self.node_list_state.select(selected);
+        } else {
+            self.select(selected);
+            self.set_mode(self.mode);
+        }
+        Ok(())
+    }
+
+    /// Saves once typing has paused for a little while.
+    async fn autosave(&mut self, database: &db::Database) {
+        if self.save_prompt.is_some() || self.autosave_paused {
+            return;
+        }
+        let Some(last_edit) = self.node_editor_state.last_edit() else { return };
+        if last_edit.elapsed() >= AUTOSAVE_DELAY {
+            self.save(database).await;
+        }
+    }
+
+    fn confirm_quit(&mut self) {
+        if let Some(prompt) = &mut self.save_prompt {
+            prompt.quitting = true;
+        }
+    }
+
+    /// Saves before quitting, returning whether it's safe to quit.
+    /// If it isn't, the save prompt asks what to do instead.
+    async fn try_quit(&mut self, database: &db::Database) -> bool {
+        if self.save(database).await {
+            return true;
+        }
+        self.confirm_quit();
+        false
+    }
+
+    /// Throws away unsaved edits by reloading the node being edited.
+    async fn discard_changes(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let Some(selected) = self.node_list_state.selected() else { return Ok(()) };
+        let Some(node) = self.node_editor_state.node() else { return Ok(()) };
+        self.children[selected] = database.get_node(node.id).await?;
+        self.select(Some(selected));
+        self.set_mode(self.mode);
+        Ok(())
+    }
+
+    async fn handle_mouse(
         mut self,
         database: &db::Database,
-        evt: KeyEvent,
+        evt: MouseEvent,
+        double_click: bool,
     ) -> anyhow::Result<Mode> {
-        if evt.code == KeyCode::Char('a') {
-            return Ok(Mode::Add(AddState::new(self)));
-        }
-        if evt.code == KeyCode::Char('f') {
-            return Ok(Mode::Find(FindState::new(database, self).await?));
+        if self.save_prompt.is_some() {
+            return Ok(Mode::Normal(self));
         }
 
-        if evt.code == KeyCode::Up {
-            self.go_up();
+        let (column, row) = (evt.column, evt.row);
+        match evt.kind {
+            MouseEventKind::Down(MouseButton::Left) if self.is_on_divider(column, row) => {
+                self.resizing = true;
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.resizing => {
+                let offset = column.saturating_sub(self.body_area.x) + 1;
+                let percentage = offset as u32 * 100 / self.body_area.width.max(1) as u32;
+                self.list_width = (percentage as u16).clamp(MIN_LIST_WIDTH, 100 - MIN_LIST_WIDTH);
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.resizing = false,
+
+            MouseEventKind::Down(MouseButton::Left) if self.list_hitbox.contains(column, row) => {
+                // Same as moving around with the keyboard,
+                // so anything being edited is saved first.
+                if !self.save(database).await {
+                    return Ok(Mode::Normal(self));
+                }
+                self.set_mode(NormalStateMode::List);
+                let Some(index) = self.list_hitbox.index_at(column, row) else {
+                    return Ok(Mode::Normal(self));
+                };
+                if double_click && self.node_list_state.selected() == Some(index) {
+                    self.choose_current_child(database).await?;
+                } else {
+                    self.select(Some(index));
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                let field = self.node_editor_state.field_at(self.editor_area, column, row);
+                if let Some(field) = field.map(NormalStateMode::from) {
+                    if field != self.mode && self.save(database).await {
+                        self.set_mode(field);
+                    }
+                }
+            }
+
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown
+                if self.list_hitbox.contains(column, row) =>
+            {
+                if !self.save(database).await {
+                    return Ok(Mode::Normal(self));
+                }
+                if evt.kind == MouseEventKind::ScrollUp {
+                    self.go_up();
+                } else {
+                    self.go_down();
+                }
+            }
+            _ => {}
         }
-        if evt.code == KeyCode::Down {
-            self.go_down();
+        Ok(Mode::Normal(self))
+    }
+
+    /// Whether `(column, row)` is on the borders between the list and the editor.
+    fn is_on_divider(&self, column: u16, row: u16) -> bool {
+        let divider = self.editor_area.x;
+        let in_body = self
+            .body_area
+            .intersects(layout::Rect::new(column, row, 1, 1));
+        in_body && (column == divider || column + 1 == divider)
+    }
+
+    /// Hands the terminal over to `editor` to edit the selected node as JSON.
+    async fn edit_selected(
+        &mut self,
+        database: &db::Database,
+        editor: &[String],
+        session: &mut TerminalSession,
+    ) -> anyhow::Result<()> {
+        if !self.save(database).await {
+            return Ok(());
         }
-        if evt.code == KeyCode::Left {
-            self.choose_parent(&database).await?;
+        let Some(selected) = self.node_list_state.selected() else { return Ok(()) };
+        let node = database.get_node(self.children[selected].id).await?;
+
+        match session.suspended(|| edit::edit_node(editor, &node))? {
+            Ok(Some(edited)) => self.update_selected(database, |node| *node = edited).await?,
+            Ok(None) => {}
+            // Most likely the JSON was broken, which isn't worth closing the UI over.
+            Err(e) => self.message = Some(format!("Couldn't edit \"{}\": {}", node.title, e)),
         }
-        if evt.code == KeyCode::Right {
-            self.choose_current_child(&database).await?;
+        Ok(())
+    }
+
+    /// Shows whichever child is selected in the node editor.
+    fn select(&mut self, selected: Option<usize>) {
+        self.node_list_state.select(selected);
+        self.node_editor_state
+            .select(selected.map(|selected| self.children[selected].clone()));
+    }
+
+    async fn handle_list_input(
+        mut self,
+        database: &db::Database,
+        action: keys::Action,
+    ) -> anyhow::Result<Mode> {
+        use keys::Action::*;
+        match (action, self.view) {
+            (Add, _) => return Ok(Mode::Add(AddState::new(self))),
+            (Find, _) => return Ok(Mode::Find(FindState::new(database, self).await?)),
+            (Next, _) => return Ok(Mode::Next(next::NextState::new(database, self).await?)),
+            (Inbox, _) => return Ok(Mode::Inbox(inbox::InboxState::new(database, self).await?)),
+            (Review, _) => {
+                return Ok(Mode::Review(
+                    review::ReviewState::new(database, self).await?,
+                ))
+            }
+            (Agenda, _) => {
+                let week_start = self.week_start;
+                return Ok(Mode::Agenda(
+                    agenda::AgendaState::new(database, self, week_start).await?,
+                ));
+            }
+            (Parents, _) => {
+                let selected = self
+                    .node_list_state
+                    .selected()
+                    .map(|selected| &self.children[selected]);
+                if let Some(child) = selected.or(self.node.as_ref()).cloned() {
+                    return Ok(Mode::Parents(
+                        parents::ParentsState::new(database, self, child).await?,
+                    ));
+                }
+            }
+            (Connect | Move | Block, _) => {
+                let sources = self.targets();
+                let purpose = match action {
+                    Move => connect::ConnectPurpose::Move,
+                    Block => connect::ConnectPurpose::Block,
+                    _ => connect::ConnectPurpose::Connect,
+                };
+                if !sources.is_empty() {
+                    return Ok(Mode::Connect(
+                        connect::ConnectState::new(database, self, sources, purpose).await?,
+                    ));
+                }
+            }
+            (Tag | SetDue, _) => {
+                let purpose = match action {
+                    Tag => bulk::PromptPurpose::Tag,
+                    _ => bulk::PromptPurpose::Due,
+                };
+                if !self.targets().is_empty() {
+                    return Ok(Mode::Prompt(bulk::PromptState::new(self, purpose)));
+                }
+            }
+            (ToggleView, _) => self.toggle_view(database).await?,
+            (Complete, _) => self.complete_targets(database).await?,
+            (Trash, _) => self.trash_targets(database).await?,
+            (Mark, _) => self.toggle_mark(),
+            (Visual, _) => self.toggle_visual(),
+            (ClearMarks, _) => self.clear_marks(),
+            (Up, _) => self.go_up(),
+            (Down, _) => self.go_down(),
+            (Back, NormalStateView::List) => self.choose_parent(database).await?,
+            (Open, NormalStateView::List) => self.choose_current_child(database).await?,
+            (Back, NormalStateView::Tree) => self.collapse(database).await?,
+            (Open, NormalStateView::Tree) => self.expand(database).await?,
+            (Descend, _) => self.choose_current_child(database).await?,
+            _ => {}
         }
         Ok(Mode::Normal(self))
     }
 
+    async fn update_selected(
+        &mut self,
+        database: &db::Database,
+        update: impl FnOnce(&mut db::Node),
+    ) -> anyhow::Result<()> {
+        let Some(selected) = self.node_list_state.selected() else { return Ok(()) };
+        // Start from what's in the database so this doesn't undo anyone else's changes.
+        let mut node = database.get_node(self.children[selected].id).await?;
+        update(&mut node);
+        database.update(&node).await?;
+        self.children[selected] = node;
+        self.select(Some(selected));
+        Ok(())
+    }
+
     async fn choose_parent(&mut self, database: &db::Database) -> anyhow::Result<()> {
         let next = self.node_path.pop();
-        if let (None, None) = (self.node_editor_state.node(), &next) {
+        if let (None, None) = (&self.node, &next) {
             return Ok(());
         }
         self.choose_node(database, next).await
     }
 
     async fn choose_current_child(&mut self, database: &db::Database) -> anyhow::Result<()> {
-        if self.children.len() == 0 {
+        if self.children.is_empty() {
             return Ok(());
         }
 
-        if let Some(current_node) = self.node_editor_state.node() {
+        let selected = self.node_list_state.selected().unwrap();
+        if let Some(current_node) = &self.node {
             self.node_path.push(current_node.clone());
         };
-        let selected = self.node_list_state.selected().unwrap();
+        self.node_path.extend(self.tree_ancestors(selected));
         let node = &self.children[selected];
         self.choose_node(database, Some(node.clone())).await
     }
 
+    async fn toggle_view(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        self.view = match self.view {
+            NormalStateView::List => NormalStateView::Tree,
+            NormalStateView::Tree => NormalStateView::List,
+        };
+        let selected = self.node_list_state.selected().map(|selected| self.children[selected].id);
+        self.load_children(database).await?;
+        // Top-level rows are in both views, so this only misses when leaving a subtree.
+        let selected = selected
+            .and_then(|id| self.children.iter().position(|child| child.id == id))
+            .or(if self.children.is_empty() { None } else { Some(0) });
+        self.select(selected);
+        Ok(())
+    }
+
+    /// Expands the selected row, or steps into it if it's already expanded.
+    async fn expand(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let Some(selected) = self.node_list_state.selected() else { return Ok(()) };
+        let row = &self.rows[selected];
+        if row.expanded {
+            self.go_down();
+            return Ok(());
+        }
+        if row.child_count == 0 {
+            return Ok(());
+        }
+        self.set_expanded(database, selected, true).await
+    }
+
+    /// Collapses the selected row, or steps out to its parent row if it's already collapsed.
+    /// Collapsing a top-level row goes up a level like the list view does.
+    async fn collapse(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let Some(selected) = self.node_list_state.selected() else {
+            return self.choose_parent(database).await;
+        };
+        let row = &self.rows[selected];
+        if row.expanded {
+            return self.set_expanded(database, selected, false).await;
+        }
+        if row.depth == 0 {
+            return self.choose_parent(database).await;
+        }
+        let parent_row = (0..selected)
+            .rev()
+            .find(|i| self.rows[*i].depth < row.depth)
+            .unwrap_or(0);
+        self.select(Some(parent_row));
+        Ok(())
+    }
+
+    async fn set_expanded(
+        &mut self,
+        database: &db::Database,
+        selected: usize,
+        expanded: bool,
+    ) -> anyhow::Result<()> {
+        let id = self.children[selected].id;
+        database.set_expanded(id, expanded).await?;
+        if expanded {
+            self.expanded.insert(id);
+        } else {
+            self.expanded.remove(&id);
+        }
+        // Only rows after the selected one change, so it stays put.
+        self.load_children(database).await?;
+        self.select(Some(selected));
+        Ok(())
+    }
+
+    /// The nodes between the one being viewed and the row at `index`,
+    /// which are only there in the tree view.
+    fn tree_ancestors(&self, index: usize) -> Vec<db::Node> {
+        let mut ancestors = Vec::new();
+        let mut depth = self.rows[index].depth;
+        for i in (0..index).rev() {
+            if depth == 0 {
+                break;
+            }
+            if self.rows[i].depth < depth {
+                depth = self.rows[i].depth;
+                ancestors.push(self.children[i].clone());
+            }
+        }
+        ancestors.reverse();
+        ancestors
+    }
+
     fn go_up(&mut self) {
-        if self.children.len() == 0 {
+        if self.children.is_empty() {
             return;
         }
 
         let selected = self.node_list_state.selected().unwrap();
         if selected > 0 {
-            self.node_list_state.select(Some(selected - 1));
+            self.select(Some(selected - 1));
         }
     }
 
     fn go_down(&mut self) {
-        if self.children.len() == 0 {
+        if self.children.is_empty() {
             return;
         }
 
         let selected = self.node_list_state.selected().unwrap();
         if selected < self.children.len() - 1 {
-            self.node_list_state.select(Some(selected + 1));
+            self.select(Some(selected + 1));
+        }
+    }
+
+    /// Views the last node of `path` with `target` selected among its children.
+    /// An empty `path` views the roots.
+    async fn jump_to(
+        &mut self,
+        database: &db::Database,
+        mut path: Vec<db::Node>,
+        target: db::NodeID,
+    ) -> anyhow::Result<()> {
+        let node = path.pop();
+        self.node_path = path;
+        self.choose_node(database, node).await?;
+        if let Some(selected) = self.children.iter().position(|child| child.id == target) {
+            self.select(Some(selected));
         }
+        Ok(())
     }
 
     async fn refresh(&mut self, database: &db::Database) -> anyhow::Result<()> {
-        self.choose_node(database, self.node_editor_state.node().map(db::Node::clone))
-            .await
+        self.choose_node(database, self.node.clone()).await
     }
 
     async fn choose_node(
@@ -302,87 +1286,265 @@ impl NormalState {
         database: &db::Database,
         node: Option<db::Node>,
     ) -> anyhow::Result<()> {
-        let child_ids = match &node {
+        self.node = node;
+        self.clear_marks();
+        self.load_children(database).await?;
+
+        if self.children.is_empty() {
+            self.select(None)
+        } else {
+            self.select(Some(0))
+        }
+
+        Ok(())
+    }
+
+    /// Loads the rows beneath `self.node`, leaving the selection for the caller to fix up.
+    async fn load_children(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let child_ids = match &self.node {
             None => database.get_roots().await,
             Some(node) => database.get_children(node.id).await,
         }?;
-        self.children = {
-            let mut children = Vec::new();
-            for child_id in child_ids.into_iter() {
-                children.push(database.get_node(child_id).await?);
-            }
-            children
-        };
-        self.node_editor_state.select(node);
 
-        if self.children.len() == 0 {
-            self.node_list_state.select(None)
-        } else {
-            self.node_list_state.select(Some(0))
+        self.children.clear();
+        self.rows.clear();
+        let mut stack: Vec<(db::NodeID, usize)> =
+            child_ids.into_iter().rev().map(|id| (id, 0)).collect();
+        while let Some((id, depth)) = stack.pop() {
+            let grandchild_ids = database.get_children(id).await?;
+            let expanded = self.view == NormalStateView::Tree
+                && self.expanded.contains(&id)
+                && !grandchild_ids.is_empty();
+            self.rows.push(TreeRow {
+                depth,
+                child_count: grandchild_ids.len(),
+                parent_count: database.get_parents(id).await?.len(),
+                expanded,
+                stats: None,
+                blockers: database.get_open_blockers(id).await?,
+            });
+            self.children.push(database.get_node(id).await?);
+            if expanded {
+                stack.extend(grandchild_ids.into_iter().rev().map(|id| (id, depth + 1)));
+            }
         }
-
+        self.visual_anchor = self.visual_anchor.filter(|anchor| *anchor < self.children.len());
         Ok(())
     }
 
-    fn render(&mut self, f: &mut Frame<impl Backend>) {
+    /// Works out stats for the rows on screen which don't have them yet.
+    /// Each one walks everything beneath its row, so doing every row on every reload
+    /// would bog down big graphs. Returns whether anything needs redrawing.
+    async fn load_visible_stats(&mut self, database: &db::Database) -> anyhow::Result<bool> {
+        let visible = self.list_hitbox.visible(self.node_list_state.selected());
+        let mut loaded = false;
+        for i in visible.start..visible.end.min(self.rows.len()) {
+            if self.rows[i].child_count > 0 && self.rows[i].stats.is_none() {
+                self.rows[i].stats = Some(database.stats(self.children[i].id).await?);
+                loaded = true;
+            }
+        }
+        Ok(loaded)
+    }
+
+    fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
         let size = f.size();
 
-        let parts = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
             .split(size);
+        // The timer goes on the right, taking no more than half the line from the breadcrumb.
+        let timer = self.timer.as_ref().map(|(node, started)| {
+            let elapsed = timer::format_elapsed(Utc::now() - *started);
+            Spans::from(vec![
+                Span::raw(" "),
+                Span::styled(elapsed, Style::default().fg(Color::Green)),
+                Span::raw(" "),
+                Span::raw(node.title.as_str()),
+            ])
+        });
+        let timer_width = timer.as_ref().map_or(0, |timer| timer.width() as u16);
+        let top_parts = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(timer_width.min(rows[0].width / 2)),
+            ])
+            .split(rows[0]);
+        let top = match &self.message {
+            Some(message) => Spans::from(Span::styled(
+                message.as_str(),
+                Style::default().fg(Color::Red),
+            )),
+            None => self.breadcrumb(top_parts[0].width as usize),
+        };
+        f.render_widget(rwidgets::Paragraph::new(top), top_parts[0]);
+        if let Some(timer) = timer {
+            f.render_widget(rwidgets::Paragraph::new(timer), top_parts[1]);
+        }
 
+        let parts = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(self.list_width),
+                Constraint::Percentage(100 - self.list_width),
+            ])
+            .split(rows[1]);
+        self.body_area = rows[1];
+        self.editor_area = parts[1];
+        self.list_hitbox
+            .update(parts[0], &self.node_list_state, vec![1; self.children.len()]);
+
+        let now = Utc::now();
+        let marking = self.is_marking();
         let items: Vec<rwidgets::ListItem> = self
             .children
             .iter()
-            .map(|node| rwidgets::ListItem::new(node.title.as_str()))
+            .zip(self.rows.iter())
+            .enumerate()
+            .map(|(i, (node, row))| {
+                let style = if !node.is_open() {
+                    Style::default()
+                        .fg(Color::DarkGray)
+                        .add_modifier(Modifier::CROSSED_OUT)
+                } else if node.due.is_some_and(|due| due < now) {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default()
+                };
+                let mark = match (marking, self.is_marked(i)) {
+                    (false, _) => "",
+                    (true, false) => "  ",
+                    (true, true) => "* ",
+                };
+                if self.view == NormalStateView::List {
+                    let mut spans = vec![
+                        Span::styled(mark, theme.highlight_style()),
+                        Span::styled(node.title.as_str(), style),
+                    ];
+                    spans.extend(row.badge());
+                    spans.extend(row.blocked_by());
+                    return rwidgets::ListItem::new(Spans::from(spans));
+                }
+
+                let marker = if row.expanded {
+                    "▾ "
+                } else if row.child_count > 0 {
+                    "▸ "
+                } else {
+                    "  "
+                };
+                let mut spans = vec![
+                    Span::styled(mark, theme.highlight_style()),
+                    Span::raw("  ".repeat(row.depth)),
+                    Span::raw(marker),
+                    Span::styled(node.title.as_str(), style),
+                ];
+                let dim = Style::default().fg(Color::DarkGray);
+                if row.child_count > 0 {
+                    spans.push(Span::styled(format!(" [{}]", row.child_count), dim));
+                }
+                if row.parent_count > 1 {
+                    spans.push(Span::styled(format!(" ({} parents)", row.parent_count), dim));
+                }
+                spans.extend(row.badge());
+                spans.extend(row.blocked_by());
+                rwidgets::ListItem::new(Spans::from(spans))
+            })
             .collect();
         let list = rwidgets::List::new(items)
             .block(
                 rwidgets::Block::default()
                     .title(self.title())
-                    .borders(rwidgets::Borders::ALL),
+                    .borders(rwidgets::Borders::ALL)
+                    .border_style(theme.border_style()),
             )
+            .highlight_style(theme.highlight_style())
             .highlight_symbol(">>");
         f.render_stateful_widget(list, parts[0], &mut self.node_list_state);
 
-	let node_editor = widgets::NodeEditor::default();
-	f.render_stateful_widget(node_editor, parts[1], &mut self.node_editor_state);
+        let node_editor = widgets::NodeEditor::default().border_style(theme.border_style());
+        f.render_stateful_widget(node_editor, parts[1], &mut self.node_editor_state);
 
-        // TODO: fix some things with this:
-        // - replace `.len()`s with something that represents the rune-length of a line
-        // - handle wrapping
-        //   - make the `x` coordinate fit horizontally along the rune-length of the last section of a wrapped line
-        //   - make the `y` coordinate account for the vertical length of wrapped lines
-        let Some(selected) = self.node_list_state.selected() else { return; };
-        use NormalStateMode::*;
-        match self.mode {
-            List => {}
-            Title => {
-                let (mut x, y) = util::cursor_offset(&self.children[selected].title);
-                x += parts[1].x + " [[ ".len() as u16;
-                f.set_cursor(x, y);
+        if let Some(prompt) = &self.save_prompt {
+            prompt.render(f);
+            return;
+        }
+        if let NormalStateMode::List = self.mode {
+            return;
+        }
+        if let Some((x, y)) = self.node_editor_state.cursor_offset(parts[1]) {
+            f.set_cursor(x, y);
+        }
+    }
+
+    /// The path from the roots to the node being viewed.
+    /// When it's too wide the top of the path is elided, since the end is what matters.
+    fn breadcrumb(&self, width: usize) -> Spans<'_> {
+        const SEPARATOR: &str = " / ";
+        let mut titles: Vec<&str> = self
+            .node_path
+            .iter()
+            .chain(self.node.iter())
+            .map(|node| node.title.as_str())
+            .collect();
+        titles.insert(0, "Root");
+
+        let mut elided = false;
+        let total_width = |titles: &[&str]| {
+            titles
+                .iter()
+                .map(|title| title.width() + SEPARATOR.len())
+                .sum::<usize>()
+        };
+        // Leave room for the "…" which replaces whatever gets dropped.
+        let ellipsis_width = 1 + SEPARATOR.len();
+        while titles.len() > 2 && total_width(&titles) + ellipsis_width > width {
+            titles.remove(1);
+            elided = true;
+        }
+        if elided {
+            titles.insert(1, "…");
+        }
+
+        let last = titles.len() - 1;
+        let mut spans = Vec::new();
+        for (i, title) in titles.into_iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(SEPARATOR, Style::default().fg(Color::DarkGray)));
             }
-            Description => {
-                let (mut x, mut y) = util::cursor_offset(&self.children[selected].description);
-                x += parts[1].x;
-                y += 1;
-                f.set_cursor(x, y);
+            if i == last {
+                spans.push(Span::styled(title, Style::default().add_modifier(Modifier::BOLD)));
+            } else {
+                spans.push(Span::raw(title));
             }
         }
+        Spans::from(spans)
     }
 
     fn title(&self) -> String {
-        match &self.node_editor_state.node() {
+        let title = match &self.node {
             None => "Root".to_string(),
             Some(node) => node.title.clone(),
+        };
+        let title = match self.view {
+            NormalStateView::List => title,
+            NormalStateView::Tree => format!("{} (tree)", title),
+        };
+        if !self.is_marking() {
+            return title;
         }
+        format!("{} ({} marked)", title, self.targets().len())
     }
 }
 
 struct AddState {
     parent: NormalState,
     node_editor_state: widgets::NodeEditorState,
+    editor_area: layout::Rect,
+    /// Shown when adding the node fails, so that it can be retried.
+    save_prompt: Option<SavePrompt>,
 }
 
 impl AddState {
@@ -390,95 +1552,279 @@ impl AddState {
         AddState {
             parent,
             node_editor_state: widgets::NodeEditorState::new(Some(db::Node::new())),
+            editor_area: layout::Rect::default(),
+            save_prompt: None,
         }
     }
 
     async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<keys::Action>,
+        evt: KeyEvent,
+    ) -> anyhow::Result<Mode> {
+        if self.save_prompt.is_some() {
+            return self.handle_save_prompt_input(database, evt).await;
+        }
+        match action {
+            Some(keys::Action::Cancel) => return Ok(Mode::Normal(self.parent)),
+            Some(keys::Action::NextField) => {
+                self.node_editor_state.focus_next();
+                return Ok(Mode::Add(self));
+            }
+            Some(keys::Action::PrevField) => {
+                self.node_editor_state.focus_prev();
+                return Ok(Mode::Add(self));
+            }
+            _ => {}
+        }
+
+        if action == Some(keys::Action::Confirm) {
+            return Ok(self.add_or_prompt(database, false).await);
+        }
+
+        self.node_editor_state.handle_input(evt);
+
+        Ok(Mode::Add(self))
+    }
+
+    async fn handle_save_prompt_input(
         mut self,
         database: &db::Database,
         evt: KeyEvent,
     ) -> anyhow::Result<Mode> {
+        let Some(prompt) = self.save_prompt.take() else { return Ok(Mode::Add(self)) };
+
         let is_ctrl_g =
             evt.modifiers.contains(KeyModifiers::CONTROL) && evt.code == KeyCode::Char('g');
         if evt.code == KeyCode::Esc || is_ctrl_g {
-            return Ok(Mode::Normal(self.parent));
+            return Ok(Mode::Add(self));
         }
-
-        if evt.modifiers.contains(KeyModifiers::CONTROL) && evt.code == KeyCode::Char('f') {
-            let node = self.node_editor_state.node().unwrap();
-            database.add(node).await?;
-            if let Some(current_node) = self.parent.node_editor_state.node() {
-                database.connect(current_node.id, node.id).await?;
+        match evt.code {
+            KeyCode::Char('r') => Ok(self.add_or_prompt(database, prompt.quitting).await),
+            KeyCode::Char('q') if prompt.quitting => {
+                let mut parent = self.parent;
+                parent.quit = parent.try_quit(database).await;
+                Ok(Mode::Normal(parent))
+            }
+            KeyCode::Char('d') if !prompt.quitting => Ok(Mode::Normal(self.parent)),
+            _ => {
+                self.save_prompt = Some(prompt);
+                Ok(Mode::Add(self))
             }
-            self.parent.refresh(database).await?;
-            return Ok(Mode::Normal(self.parent));
         }
+    }
 
-        self.node_editor_state.handle_input(evt);
+    /// Adds the node and goes back to the parent, quitting too if `quitting` is set.
+    /// If adding fails this stays put, with a prompt to retry.
+    async fn add_or_prompt(mut self, database: &db::Database, quitting: bool) -> Mode {
+        if let Err(e) = self.add(database).await {
+            self.save_prompt = Some(SavePrompt {
+                problem: SaveProblem::Failed(e.to_string()),
+                quitting,
+            });
+            return Mode::Add(self);
+        }
+        let mut parent = self.parent;
+        if quitting {
+            parent.quit = parent.try_quit(database).await;
+        }
+        Mode::Normal(parent)
+    }
 
-        Ok(Mode::Add(self))
+    /// Adds the node being edited beneath the node the parent is showing.
+    async fn add(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let node = self.node_editor_state.node().unwrap();
+        database.add(node).await?;
+        if let Some(current_node) = &self.parent.node {
+            database.connect(current_node.id, node.id).await?;
+        }
+        self.parent.refresh(database).await
     }
 
-    fn render(&mut self, f: &mut Frame<impl Backend>) {
-        self.parent.render(f);
+    fn handle_paste(mut self, text: String) -> Mode {
+        self.node_editor_state.paste(&text);
+        Mode::Add(self)
+    }
 
-        let rect = {
-            let margin = layout::Margin {
-                horizontal: 8,
-                vertical: 4,
-            };
-            f.size().inner(&margin)
-        };
+    fn handle_mouse(mut self, evt: MouseEvent) -> Mode {
+        if self.save_prompt.is_none() && evt.kind == MouseEventKind::Down(MouseButton::Left) {
+            let field = self
+                .node_editor_state
+                .field_at(self.editor_area, evt.column, evt.row);
+            if let Some(field) = field {
+                self.node_editor_state.focus(field);
+            }
+        }
+        Mode::Add(self)
+    }
+
+    fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let rect = modal_area(f.size());
         f.render_widget(rwidgets::Clear, rect);
 
-        let node_editor = widgets::NodeEditor::default();
+        let node_editor = widgets::NodeEditor::default().border_style(theme.border_style());
         f.render_stateful_widget(node_editor, rect, &mut self.node_editor_state);
+        self.editor_area = rect;
 
         if let Some((x, y)) = self.node_editor_state.cursor_offset(rect) {
             f.set_cursor(x, y);
         }
+        if let Some(prompt) = &self.save_prompt {
+            prompt.render(f);
+        }
     }
 }
 
 struct FindState {
     parent: NormalState,
-    search_string: String,
-    candidates: Vec<db::Node>,
-    candidate_list_state: rwidgets::ListState,
+    picker: NodePicker,
 }
 
 impl FindState {
     async fn new(database: &db::Database, parent: NormalState) -> anyhow::Result<Self> {
-        let mut find_state = FindState {
+        Ok(FindState {
             parent,
-            search_string: "".to_string(),
-            candidates: vec![],
-            candidate_list_state: rwidgets::ListState::default(),
-        };
-        find_state.update_search_candidates(database).await?;
-        Ok(find_state)
+            picker: NodePicker::new(database).await?,
+        })
     }
 
     async fn handle_input(
         mut self,
         database: &db::Database,
+        action: Option<keys::Action>,
         evt: KeyEvent,
     ) -> anyhow::Result<Mode> {
-        let is_ctrl_g =
-            evt.modifiers.contains(KeyModifiers::CONTROL) && evt.code == KeyCode::Char('g');
-        if evt.code == KeyCode::Esc || is_ctrl_g {
-            return Ok(Mode::Normal(self.parent));
+        match action {
+            Some(keys::Action::Cancel) => return Ok(Mode::Normal(self.parent)),
+            Some(keys::Action::Confirm) => return self.choose(database).await,
+            Some(keys::Action::Up) => self.picker.go_up(),
+            Some(keys::Action::Down) => self.picker.go_down(),
+            _ => self.picker.handle_input(database, evt).await?,
         }
+        Ok(Mode::Find(self))
+    }
+
+    async fn handle_paste(mut self, database: &db::Database, text: String) -> anyhow::Result<Mode> {
+        self.picker.handle_paste(database, text).await?;
+        Ok(Mode::Find(self))
+    }
 
-        if evt.modifiers.contains(KeyModifiers::CONTROL) && evt.code == KeyCode::Char('f') {
+    async fn handle_mouse(
+        mut self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        if self.picker.handle_mouse(evt, double_click) {
             return self.choose(database).await;
         }
+        Ok(Mode::Find(self))
+    }
+
+    async fn choose(self, database: &db::Database) -> anyhow::Result<Mode> {
+        let Some(selected) = self.picker.selected().cloned() else { return Ok(Mode::Find(self)) };
+        // Reuse the parent so that things like the view carry over.
+        // The path has to come from the database since we didn't walk here,
+        // otherwise going left would skip straight back to the roots.
+        let mut parent = self.parent;
+        parent.node_path = database.get_path(selected.id).await?;
+        parent.choose_node(database, Some(selected)).await?;
+        Ok(Mode::Normal(parent))
+    }
+
+    fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let rect = modal_area(f.size());
+        f.render_widget(rwidgets::Clear, rect);
+
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Percentage(100)])
+            .split(rect);
+        self.picker.render(f, parts[0], parts[1], theme);
+    }
+}
+
+/// The area covered by dialogs which render on top of another mode.
+fn modal_area(size: layout::Rect) -> layout::Rect {
+    let margin = layout::Margin {
+        horizontal: 8,
+        vertical: 4,
+    };
+    size.inner(&margin)
+}
+
+/// Lists the keys which do something in `context`.
+fn render_help(
+    f: &mut Frame<impl Backend>,
+    keymap: &keymap::Keymap,
+    context: keymap::Context,
+    theme: &config::Theme,
+) {
+    let rect = modal_area(f.size());
+    f.render_widget(rwidgets::Clear, rect);
+
+    let bindings = keymap.bindings(context);
+    let keys: Vec<String> = bindings
+        .iter()
+        .map(|(_, keys)| {
+            keys.iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<String>>()
+                .join(", ")
+        })
+        .collect();
+    let width = keys.iter().map(|keys| keys.width()).max().unwrap_or(0);
+    let lines: Vec<Spans> = bindings
+        .iter()
+        .zip(keys.iter())
+        .map(|((action, _), keys)| {
+            Spans::from(vec![
+                Span::styled(
+                    format!("{:width$}  ", keys, width = width),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(action.description()),
+            ])
+        })
+        .collect();
+
+    let help = rwidgets::Paragraph::new(lines).block(
+        rwidgets::Block::default()
+            .title(format!("Keys: {} (press any key to close)", context.name()))
+            .borders(rwidgets::Borders::all())
+            .border_style(theme.border_style()),
+    );
+    f.render_widget(help, rect);
+}
+
+/// A search box over a fuzzy-found list of nodes,
+/// shared by every mode which needs the user to pick a node.
+struct NodePicker {
+    search_string: String,
+    candidates: Vec<db::Node>,
+    candidate_list: widgets::SelectableList,
+}
+
+impl NodePicker {
+    async fn new(database: &db::Database) -> anyhow::Result<Self> {
+        let mut picker = NodePicker {
+            search_string: "".to_string(),
+            candidates: vec![],
+            candidate_list: widgets::SelectableList::default(),
+        };
+        picker.update_search_candidates(database).await?;
+        Ok(picker)
+    }
 
+    async fn handle_input(&mut self, database: &db::Database, evt: KeyEvent) -> anyhow::Result<()> {
         let mut string_changed = false;
         match evt.code {
-            KeyCode::Up => self.go_up(),
-            KeyCode::Down => self.go_down(),
-
+            KeyCode::Char(_) if evt.modifiers.contains(KeyModifiers::CONTROL) => {}
             KeyCode::Char(c) => {
                 self.search_string.push(c);
                 string_changed = true;
@@ -494,69 +1840,62 @@ impl FindState {
         if string_changed {
             self.update_search_candidates(database).await?;
         }
+        Ok(())
+    }
 
-        Ok(Mode::Find(self))
+    async fn handle_paste(&mut self, database: &db::Database, text: String) -> anyhow::Result<()> {
+        self.search_string
+            .extend(text.chars().map(|c| if c.is_control() { ' ' } else { c }));
+        self.update_search_candidates(database).await
     }
 
-    async fn choose(self, database: &db::Database) -> anyhow::Result<Mode> {
-        let Some(selected) = self.candidate_list_state.selected() else { return Ok(Mode::Find(self)) };
-        Ok(Mode::Normal(
-            NormalState::new(database, Some(self.candidates[selected].clone())).await?,
-        ))
+    /// Selects or scrolls through candidates,
+    /// returning whether the selected one was double clicked.
+    fn handle_mouse(&mut self, evt: MouseEvent, double_click: bool) -> bool {
+        let len = self.candidates.len();
+        self.candidate_list
+            .handle_mouse(evt, double_click, len, Some)
+    }
+
+    fn selected(&self) -> Option<&db::Node> {
+        let selected = self.candidate_list.selected()?;
+        self.candidates.get(selected)
     }
 
     fn go_up(&mut self) {
-        let Some(selected) = self.candidate_list_state.selected() else { return };
-        if selected > 0 {
-            self.candidate_list_state.select(Some(selected - 1));
-        }
+        self.candidate_list.go_up();
     }
 
     fn go_down(&mut self) {
-        let Some(selected) = self.candidate_list_state.selected() else { return };
-        if selected < self.candidates.len() - 1 {
-            self.candidate_list_state.select(Some(selected + 1));
-        }
+        self.candidate_list.go_down(self.candidates.len());
     }
 
     async fn update_search_candidates(&mut self, database: &db::Database) -> anyhow::Result<()> {
         let mut candidates = find_candidates(&self.search_string, database).await?;
-        candidates.sort_by_key(|(_, distance)| distance.clone());
+        candidates.sort_by_key(|(_, distance)| *distance);
 
         self.candidates = candidates.into_iter().map(|(node, _)| node).collect();
-        if self.candidates.len() == 0 {
-            self.candidate_list_state.select(None);
-        } else {
-            self.candidate_list_state.select(Some(0));
-        }
+        self.candidate_list.select_first(self.candidates.len());
         Ok(())
     }
 
-    fn render(&mut self, f: &mut Frame<impl Backend>) {
-        self.parent.render(f);
-
-        let rect = {
-            let margin = layout::Margin {
-                horizontal: 8,
-                vertical: 4,
-            };
-            f.size().inner(&margin)
-        };
-        f.render_widget(rwidgets::Clear, rect);
-
-        let parts = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(3), Constraint::Percentage(100)])
-            .split(rect);
-
+    fn render(
+        &mut self,
+        f: &mut Frame<impl Backend>,
+        search_area: layout::Rect,
+        list_area: layout::Rect,
+        theme: &config::Theme,
+    ) {
         // TODO: handle rendering search strings which are longer than the width of this block
-        let top = rwidgets::Paragraph::new(self.search_string.clone())
-            .block(rwidgets::Block::default().borders(rwidgets::Borders::all()));
-        f.render_widget(top, parts[0]);
-        f.set_cursor(
-            parts[0].x + 1 + self.search_string.len() as u16,
-            parts[0].y + 1,
+        let top = rwidgets::Paragraph::new(self.search_string.clone()).block(
+            rwidgets::Block::default()
+                .borders(rwidgets::Borders::all())
+                .border_style(theme.border_style()),
         );
+        f.render_widget(top, search_area);
+        // The search string isn't wrapped, so it's all on the one line.
+        let (x, _) = util::cursor_offset(&self.search_string, self.search_string.len(), u16::MAX);
+        f.set_cursor(search_area.x + x, search_area.y + 1);
 
         let bottom = rwidgets::List::new(
             self.candidates
@@ -564,23 +1903,193 @@ impl FindState {
                 .map(|node| rwidgets::ListItem::new(node.title.to_owned()))
                 .collect::<Vec<rwidgets::ListItem>>(),
         )
-        .block(rwidgets::Block::default().borders(rwidgets::Borders::all()))
+        .block(
+            rwidgets::Block::default()
+                .borders(rwidgets::Borders::all())
+                .border_style(theme.border_style()),
+        )
+        .highlight_style(theme.highlight_style())
         .highlight_symbol(">>");
-        f.render_stateful_widget(bottom, parts[1], &mut self.candidate_list_state);
+        let heights = vec![1; self.candidates.len()];
+        let selected = self.candidate_list.selected();
+        self.candidate_list
+            .render(f, bottom, list_area, heights, selected);
     }
 }
 
-struct RawModeGuard {}
+/// Picks out double clicks, which crossterm doesn't report on its own.
+#[derive(Default)]
+struct ClickTracker {
+    last_click: Option<(Instant, u16, u16)>,
+}
 
-impl RawModeGuard {
-    pub fn new() -> anyhow::Result<Self> {
-        enable_raw_mode()?;
-        Ok(RawModeGuard {})
+impl ClickTracker {
+    fn is_double_click(&mut self, evt: &MouseEvent) -> bool {
+        if evt.kind != MouseEventKind::Down(MouseButton::Left) {
+            return false;
+        }
+        let is_double = self.last_click.is_some_and(|(at, column, row)| {
+            at.elapsed() < DOUBLE_CLICK_DELAY && column == evt.column && row == evt.row
+        });
+        // A third click starts over rather than counting as another double click.
+        self.last_click = if is_double {
+            None
+        } else {
+            Some((Instant::now(), evt.column, evt.row))
+        };
+        is_double
     }
 }
 
-impl Drop for RawModeGuard {
+/// Owns the terminal while the UI is running:
+/// raw mode, the alternate screen, bracketed paste and mouse capture.
+/// Dropping it hands the terminal back the way it was,
+/// and a panic hook does the same so that crashes are readable.
+type PanicHook = dyn Fn(&PanicHookInfo<'_>) + Send + Sync + 'static;
+
+struct TerminalSession {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    /// Whatever handled panics before this session, put back once it's over.
+    previous_hook: Arc<PanicHook>,
+}
+
+impl TerminalSession {
+    fn new() -> anyhow::Result<Self> {
+        let previous_hook: Arc<PanicHook> = Arc::from(std::panic::take_hook());
+        let hook = previous_hook.clone();
+        std::panic::set_hook(Box::new(move |info| {
+            let _ = leave_terminal();
+            hook(info);
+        }));
+
+        let terminal =
+            enter_terminal().and_then(|()| Terminal::new(CrosstermBackend::new(std::io::stdout())));
+        match terminal {
+            Ok(terminal) => Ok(TerminalSession {
+                terminal,
+                previous_hook,
+            }),
+            Err(e) => {
+                // There's no session to drop, so undo whatever did get set up here instead.
+                let _ = leave_terminal();
+                restore_panic_hook(previous_hook);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Gives the terminal to something else while `f` runs, like an editor.
+    fn suspended<T>(&mut self, f: impl FnOnce() -> T) -> anyhow::Result<T> {
+        leave_terminal()?;
+        let result = f();
+        enter_terminal()?;
+        // Whatever ran has drawn over the screen, so redraw all of it.
+        self.terminal.clear()?;
+        Ok(result)
+    }
+
+    /// Stops the process the way Ctrl+Z would in a shell,
+    /// which raw mode otherwise prevents, and picks back up once it's resumed.
+    fn suspend(&mut self) -> anyhow::Result<()> {
+        #[cfg(unix)]
+        self.suspended(|| {
+            // SAFETY: raising a signal has no memory safety requirements.
+            unsafe { libc::raise(libc::SIGTSTP) };
+        })?;
+        Ok(())
+    }
+}
+
+impl Drop for TerminalSession {
     fn drop(&mut self) {
-        disable_raw_mode().expect("Failed to disable raw mode");
+        // Nothing more can be done if this fails, and panicking here would just make it worse.
+        let _ = leave_terminal();
+        // Setting the hook panics while unwinding, and the hook has already run by then anyway.
+        if !std::thread::panicking() {
+            restore_panic_hook(self.previous_hook.clone());
+        }
+    }
+}
+
+fn restore_panic_hook(previous_hook: Arc<PanicHook>) {
+    std::panic::set_hook(Box::new(move |info| previous_hook(info)));
+}
+
+fn enter_terminal() -> std::io::Result<()> {
+    enable_raw_mode()?;
+    crossterm::execute!(
+        std::io::stdout(),
+        EnterAlternateScreen,
+        EnableBracketedPaste,
+        EnableMouseCapture,
+    )
+}
+
+fn leave_terminal() -> std::io::Result<()> {
+    crossterm::execute!(
+        std::io::stdout(),
+        DisableMouseCapture,
+        DisableBracketedPaste,
+        LeaveAlternateScreen,
+        cursor::Show,
+    )?;
+    disable_raw_mode()
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quit__keeps_node_being_added() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let temp_dir = tempfile::tempdir()?;
+            let database = db::Database::create(temp_dir.path().join("graph.sqlite3")).await?;
+            let parent = NormalState::new(&database, None, Weekday::Mon).await?;
+            let mut state = AddState::new(parent);
+            state.node_editor_state.paste("half typed");
+
+            assert!(Mode::Add(state).quit(&database).await?.is_none());
+            let mut titles = Vec::new();
+            for root in database.get_roots().await?.into_iter() {
+                titles.push(database.get_node(root).await?.title);
+            }
+            assert_eq!(titles, vec!["half typed".to_string()]);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_quit__prompts_when_add_fails() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let temp_dir = tempfile::tempdir()?;
+            let database = db::Database::create(temp_dir.path().join("graph.sqlite3")).await?;
+            let parent = NormalState::new(&database, None, Weekday::Mon).await?;
+            let mut state = AddState::new(parent);
+            state.node_editor_state.paste("half typed");
+            // Adding a node with the same uuid again fails.
+            database.add(state.node_editor_state.node().unwrap()).await?;
+
+            let Some(Mode::Add(state)) = Mode::Add(state).quit(&database).await? else {
+                panic!("expected to stay in the add dialog");
+            };
+            assert!(state.save_prompt.is_some_and(|prompt| prompt.quitting));
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_quit__skips_empty_add() -> anyhow::Result<()> {
+        async_std::task::block_on(async {
+            let temp_dir = tempfile::tempdir()?;
+            let database = db::Database::create(temp_dir.path().join("graph.sqlite3")).await?;
+            let parent = NormalState::new(&database, None, Weekday::Mon).await?;
+            let state = AddState::new(parent);
+
+            assert!(Mode::Add(state).quit(&database).await?.is_none());
+            assert!(database.get_roots().await?.is_empty());
+            Ok(())
+        })
     }
 }
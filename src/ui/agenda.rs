@@ -0,0 +1,547 @@
+use std::collections::BTreeMap;
+
+use chrono::DateTime;
+use chrono::Datelike;
+use chrono::Duration;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::Utc;
+use chrono::Weekday;
+use crossterm::event::MouseEvent;
+use ratatui::backend::Backend;
+use ratatui::layout::Constraint;
+use ratatui::layout::Direction;
+use ratatui::layout::Layout;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
+use ratatui::widgets as rwidgets;
+use ratatui::Frame;
+
+use crate::config;
+use crate::db;
+use crate::keys::Action;
+use crate::ui::util;
+use crate::ui::widgets;
+use crate::ui::Mode;
+use crate::ui::NormalState;
+
+/// How far away something on the agenda is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum AgendaGroup {
+    Overdue,
+    Today,
+    Tomorrow,
+    ThisWeek,
+    Later,
+}
+
+impl AgendaGroup {
+    fn of(day: NaiveDate, today: NaiveDate, week_start: Weekday) -> Self {
+        use AgendaGroup::*;
+        let days_into_week =
+            (7 + today.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+        let next_week = today + Duration::days(7 - days_into_week as i64);
+
+        if day < today {
+            Overdue
+        } else if day == today {
+            Today
+        } else if day == today.succ_opt().unwrap_or(today) {
+            Tomorrow
+        } else if day < next_week {
+            ThisWeek
+        } else {
+            Later
+        }
+    }
+
+    fn name(self) -> &'static str {
+        use AgendaGroup::*;
+        match self {
+            Overdue => "Overdue",
+            Today => "Today",
+            Tomorrow => "Tomorrow",
+            ThisWeek => "This week",
+            Later => "Later",
+        }
+    }
+}
+
+/// The date a node is listed under: when it's scheduled, or else when it's due.
+fn agenda_date(node: &db::Node) -> Option<DateTime<Utc>> {
+    node.scheduled.or(node.due)
+}
+
+/// Which part of the agenda a node is listed in.
+/// Only missing a due date makes a node overdue:
+/// one which was scheduled in the past is just ready to work on today.
+fn agenda_group(node: &db::Node, today: NaiveDate, week_start: Weekday) -> Option<AgendaGroup> {
+    if node.due.is_some_and(|due| local_day(due) < today) {
+        return Some(AgendaGroup::Overdue);
+    }
+    let day = local_day(agenda_date(node)?).max(today);
+    Some(AgendaGroup::of(day, today, week_start))
+}
+
+/// The date that moving a node earlier or later changes.
+/// Overdue nodes have their due date moved, since that's what files them as overdue;
+/// otherwise it's the scheduled date, if there is one.
+fn rescheduled_date(node: &mut db::Node, today: NaiveDate) -> Option<&mut DateTime<Utc>> {
+    let overdue = node.due.is_some_and(|due| local_day(due) < today);
+    if overdue || node.scheduled.is_none() {
+        node.due.as_mut()
+    } else {
+        node.scheduled.as_mut()
+    }
+}
+
+fn local_day(date_time: DateTime<Utc>) -> NaiveDate {
+    date_time.with_timezone(&Local).date_naive()
+}
+
+/// The weeks of the month containing `day`, each starting on `week_start`,
+/// with `None` for the days that fall in the months either side.
+fn month_grid(day: NaiveDate, week_start: Weekday) -> Vec<[Option<NaiveDate>; 7]> {
+    let first = day.with_day(1).unwrap();
+    let lead = (7 + first.weekday().num_days_from_monday() - week_start.num_days_from_monday()) % 7;
+
+    let mut weeks = Vec::new();
+    let mut week = [None; 7];
+    let mut column = lead as usize;
+    let mut current = first;
+    while current.month() == first.month() {
+        week[column] = Some(current);
+        column += 1;
+        if column == 7 {
+            weeks.push(week);
+            week = [None; 7];
+            column = 0;
+        }
+        current = current.succ_opt().unwrap();
+    }
+    if column > 0 {
+        weeks.push(week);
+    }
+    weeks
+}
+
+/// One line of the agenda list.
+enum AgendaRow {
+    Header(AgendaGroup),
+    /// An index into `AgendaState::items`.
+    Item(usize),
+}
+
+/// Lists every open node with a date, soonest first, next to a calendar of the month.
+pub(super) struct AgendaState {
+    parent: NormalState,
+    week_start: Weekday,
+    items: Vec<db::Node>,
+    rows: Vec<AgendaRow>,
+    /// Selects among `items`, though it's `rows` which get drawn.
+    item_list: widgets::SelectableList,
+}
+
+impl AgendaState {
+    pub(super) async fn new(
+        database: &db::Database,
+        parent: NormalState,
+        week_start: Weekday,
+    ) -> anyhow::Result<Self> {
+        let mut state = AgendaState {
+            parent,
+            week_start,
+            items: vec![],
+            rows: vec![],
+            item_list: widgets::SelectableList::default(),
+        };
+        state.refresh(database).await?;
+        Ok(state)
+    }
+
+    pub(super) fn into_parent(self) -> NormalState {
+        self.parent
+    }
+
+    pub(super) async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<Action>,
+    ) -> anyhow::Result<Mode> {
+        match action {
+            Some(Action::Cancel) => {
+                // Rescheduling may have changed what the parent is showing.
+                self.parent.refresh(database).await?;
+                return Ok(Mode::Normal(self.parent));
+            }
+            Some(Action::Up) => self.item_list.go_up(),
+            Some(Action::Down) => self.item_list.go_down(self.items.len()),
+            Some(Action::Later) => self.reschedule_selected(database, 1).await?,
+            Some(Action::Earlier) => self.reschedule_selected(database, -1).await?,
+            Some(Action::Confirm) => return self.jump_to_selected(database).await,
+            _ => {}
+        }
+        Ok(Mode::Agenda(self))
+    }
+
+    pub(super) async fn handle_mouse(
+        mut self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        let rows = &self.rows;
+        let item_at = |row: usize| match rows[row] {
+            AgendaRow::Item(index) => Some(index),
+            AgendaRow::Header(_) => None,
+        };
+        if self
+            .item_list
+            .handle_mouse(evt, double_click, self.items.len(), item_at)
+        {
+            return self.jump_to_selected(database).await;
+        }
+        Ok(Mode::Agenda(self))
+    }
+
+    /// Moves the selected node's date by `days`, keeping the time of day.
+    /// Only the date it's listed under moves, so a scheduled node keeps its deadline.
+    async fn reschedule_selected(
+        &mut self,
+        database: &db::Database,
+        days: i64,
+    ) -> anyhow::Result<()> {
+        let Some(selected) = self.item_list.selected() else { return Ok(()) };
+        // Start from what's in the database so this doesn't undo anyone else's changes.
+        let mut node = database.get_node(self.items[selected].id).await?;
+        let today = Local::now().date_naive();
+        let Some(date) = rescheduled_date(&mut node, today) else { return Ok(()) };
+        *date += Duration::days(days);
+        database.update(&node).await?;
+
+        self.refresh(database).await?;
+        self.item_list
+            .select(self.items.iter().position(|item| item.id == node.id));
+        Ok(())
+    }
+
+    async fn jump_to_selected(self, database: &db::Database) -> anyhow::Result<Mode> {
+        let Some(selected) = self.item_list.selected() else {
+            return Ok(Mode::Agenda(self));
+        };
+        let node = &self.items[selected];
+        let path = database.get_path(node.id).await?;
+
+        let id = node.id;
+        let mut parent = self.parent;
+        parent.jump_to(database, path, id).await?;
+        Ok(Mode::Normal(parent))
+    }
+
+    async fn refresh(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let mut items = Vec::new();
+        for id in database.get_dated_nodes().await?.into_iter() {
+            let node = database.get_node(id).await?;
+            if node.is_open() {
+                items.push(node);
+            }
+        }
+        let today = Local::now().date_naive();
+        let group = |node: &db::Node| agenda_group(node, today, self.week_start);
+        items.sort_by(|a, b| {
+            group(a)
+                .cmp(&group(b))
+                .then_with(|| agenda_date(a).cmp(&agenda_date(b)))
+                .then_with(|| a.title.cmp(&b.title))
+        });
+        self.items = items;
+
+        self.rows.clear();
+        let mut last_group = None;
+        for (i, node) in self.items.iter().enumerate() {
+            let Some(group) = group(node) else { continue };
+            if last_group != Some(group) {
+                self.rows.push(AgendaRow::Header(group));
+                last_group = Some(group);
+            }
+            self.rows.push(AgendaRow::Item(i));
+        }
+
+        self.item_list.select_first(self.items.len());
+        Ok(())
+    }
+
+    pub(super) fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        let size = f.size();
+        f.render_widget(rwidgets::Clear, size);
+
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(20), Constraint::Length(CALENDAR_WIDTH)])
+            .split(size);
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(9), Constraint::Min(0)])
+            .split(columns[1]);
+
+        self.render_list(f, columns[0], theme);
+        self.render_calendar(f, right[0], theme);
+        self.render_details(f, right[1], theme);
+    }
+
+    fn render_list(
+        &mut self,
+        f: &mut Frame<impl Backend>,
+        area: ratatui::layout::Rect,
+        theme: &config::Theme,
+    ) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let items: Vec<rwidgets::ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                AgendaRow::Header(group) => {
+                    let mut style = Style::default().add_modifier(Modifier::BOLD);
+                    if *group == AgendaGroup::Overdue {
+                        style = style.fg(Color::Red);
+                    }
+                    rwidgets::ListItem::new(Span::styled(group.name(), style))
+                }
+                AgendaRow::Item(index) => {
+                    let node = &self.items[*index];
+                    let (kind, date) = match (node.scheduled, node.due) {
+                        (Some(scheduled), _) => ("scheduled", scheduled),
+                        (None, Some(due)) => ("due", due),
+                        (None, None) => unreachable!("Undated nodes aren't on the agenda"),
+                    };
+                    rwidgets::ListItem::new(Spans::from(vec![
+                        Span::raw(node.title.clone()),
+                        Span::styled(format!("  {} {}", kind, util::format_date_time(date)), dim),
+                    ]))
+                }
+            })
+            .collect();
+
+        let selected = self.item_list.selected();
+        let selected_row = self.rows.iter().position(|row| match row {
+            AgendaRow::Item(index) => Some(*index) == selected,
+            AgendaRow::Header(_) => false,
+        });
+
+        let list = rwidgets::List::new(items)
+            .block(
+                rwidgets::Block::default()
+                    .title("Agenda")
+                    .borders(rwidgets::Borders::all())
+                    .border_style(theme.border_style()),
+            )
+            .highlight_style(theme.highlight_style())
+            .highlight_symbol(">>");
+        let heights = vec![1; self.rows.len()];
+        self.item_list.render(f, list, area, heights, selected_row);
+    }
+
+    /// Draws the month around the selected node, with how many things land on each day.
+    fn render_calendar(
+        &self,
+        f: &mut Frame<impl Backend>,
+        area: ratatui::layout::Rect,
+        theme: &config::Theme,
+    ) {
+        let today = Local::now().date_naive();
+        let selected_day = self
+            .item_list
+            .selected()
+            .and_then(|selected| agenda_date(&self.items[selected]))
+            .map(local_day);
+        let month = selected_day.unwrap_or(today);
+
+        let mut counts: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        for date in self.items.iter().filter_map(agenda_date) {
+            *counts.entry(local_day(date)).or_default() += 1;
+        }
+
+        let mut header = Vec::new();
+        let mut weekday = self.week_start;
+        for _ in 0..7 {
+            header.push(Span::raw(format!("{:<6}", &weekday.to_string()[..2])));
+            weekday = weekday.succ();
+        }
+        let mut lines = vec![Spans::from(header)];
+
+        for week in month_grid(month, self.week_start) {
+            let spans = week
+                .iter()
+                .map(|day| {
+                    let Some(day) = day else { return Span::raw(" ".repeat(6)) };
+                    let count = counts.get(day).copied().unwrap_or(0);
+                    let text = if count > 0 {
+                        format!("{:>2}({})", day.day(), count)
+                    } else {
+                        format!("{:>2}", day.day())
+                    };
+
+                    let mut style = Style::default();
+                    if count > 0 {
+                        style = theme.highlight_style().add_modifier(Modifier::BOLD);
+                        if *day < today {
+                            style = style.fg(Color::Red);
+                        }
+                    }
+                    if *day == today {
+                        style = style.add_modifier(Modifier::UNDERLINED);
+                    }
+                    if Some(*day) == selected_day {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                    Span::styled(format!("{:<6}", text), style)
+                })
+                .collect::<Vec<Span>>();
+            lines.push(Spans::from(spans));
+        }
+
+        let calendar = rwidgets::Paragraph::new(lines).block(
+            rwidgets::Block::default()
+                .title(month.format("%B %Y").to_string())
+                .borders(rwidgets::Borders::all())
+                .border_style(theme.border_style()),
+        );
+        f.render_widget(calendar, area);
+    }
+
+    fn render_details(
+        &self,
+        f: &mut Frame<impl Backend>,
+        area: ratatui::layout::Rect,
+        theme: &config::Theme,
+    ) {
+        let mut lines = Vec::new();
+        if let Some(selected) = self.item_list.selected() {
+            let node = &self.items[selected];
+            lines.push(Spans::from(Span::styled(
+                node.title.as_str(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )));
+            if let Some(scheduled) = node.scheduled {
+                lines.push(Spans::from(format!(
+                    "Scheduled {}",
+                    util::format_date_time(scheduled)
+                )));
+            }
+            if let Some(due) = node.due {
+                let style = if due < Utc::now() {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default()
+                };
+                lines.push(Spans::from(Span::styled(
+                    format!("Due {}", util::format_date_time(due)),
+                    style,
+                )));
+            }
+        }
+
+        let details = rwidgets::Paragraph::new(lines)
+            .wrap(rwidgets::Wrap { trim: false })
+            .block(
+                rwidgets::Block::default()
+                    .borders(rwidgets::Borders::all())
+                    .border_style(theme.border_style()),
+            );
+        f.render_widget(details, area);
+    }
+}
+
+/// Seven six-column days, plus the borders.
+const CALENDAR_WIDTH: u16 = 7 * 6 + 2;
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn test_agenda_group__of() {
+        // A Wednesday.
+        let today = date(2023, 4, 5);
+        let group = |day| AgendaGroup::of(day, today, Weekday::Mon);
+        assert_eq!(group(date(2023, 4, 4)), AgendaGroup::Overdue);
+        assert_eq!(group(date(2023, 4, 5)), AgendaGroup::Today);
+        assert_eq!(group(date(2023, 4, 6)), AgendaGroup::Tomorrow);
+        assert_eq!(group(date(2023, 4, 9)), AgendaGroup::ThisWeek);
+        assert_eq!(group(date(2023, 4, 10)), AgendaGroup::Later);
+    }
+
+    #[test]
+    fn test_agenda_group__of__week_start() {
+        let today = date(2023, 4, 5);
+        let group = |day| AgendaGroup::of(day, today, Weekday::Sun);
+        assert_eq!(group(date(2023, 4, 8)), AgendaGroup::ThisWeek);
+        assert_eq!(group(date(2023, 4, 9)), AgendaGroup::Later);
+    }
+
+    #[test]
+    fn test_agenda_group__scheduled_in_past_due_later() {
+        let today = Local::now().date_naive();
+        let mut node = db::Node::new();
+        node.scheduled = Some(Utc::now() - Duration::days(1));
+        node.due = Some(Utc::now() + Duration::days(7));
+        assert_eq!(
+            agenda_group(&node, today, Weekday::Mon),
+            Some(AgendaGroup::Today)
+        );
+
+        node.due = Some(Utc::now() - Duration::days(2));
+        assert_eq!(
+            agenda_group(&node, today, Weekday::Mon),
+            Some(AgendaGroup::Overdue)
+        );
+        assert_eq!(agenda_group(&db::Node::new(), today, Weekday::Mon), None);
+    }
+
+    #[test]
+    fn test_rescheduled_date__overdue_moves_due() {
+        let today = Local::now().date_naive();
+        let mut node = db::Node::new();
+        let scheduled = Utc::now() - Duration::days(3);
+        let due = Utc::now() - Duration::days(2);
+        node.scheduled = Some(scheduled);
+        node.due = Some(due);
+        assert_eq!(rescheduled_date(&mut node, today).copied(), Some(due));
+
+        node.due = Some(Utc::now() + Duration::days(2));
+        assert_eq!(rescheduled_date(&mut node, today).copied(), Some(scheduled));
+        assert_eq!(rescheduled_date(&mut db::Node::new(), today), None);
+    }
+
+    #[test]
+    fn test_month_grid() {
+        // April 2023 starts on a Saturday and ends on a Sunday.
+        let weeks = month_grid(date(2023, 4, 17), Weekday::Mon);
+        assert_eq!(weeks.len(), 5);
+        assert_eq!(weeks[0][4], None);
+        assert_eq!(weeks[0][5], Some(date(2023, 4, 1)));
+        assert_eq!(weeks[4][6], Some(date(2023, 4, 30)));
+
+        let weeks = month_grid(date(2023, 4, 17), Weekday::Sat);
+        assert_eq!(weeks[0][0], Some(date(2023, 4, 1)));
+        assert_eq!(weeks[4][1], Some(date(2023, 4, 30)));
+        assert_eq!(weeks[4][2], None);
+    }
+
+    #[test]
+    fn test_month_grid__six_weeks() {
+        // July 2023 starts on a Saturday and ends on a Monday.
+        let weeks = month_grid(date(2023, 7, 1), Weekday::Mon);
+        assert_eq!(weeks.len(), 6);
+        assert_eq!(weeks[5][0], Some(date(2023, 7, 31)));
+        assert_eq!(weeks[5][1], None);
+    }
+}
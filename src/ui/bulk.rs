@@ -0,0 +1,312 @@
+use std::collections::BTreeSet;
+
+use chrono::Utc;
+use crossterm::event::KeyEvent;
+use ratatui::backend::Backend;
+use ratatui::layout;
+use ratatui::style::Color;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::widgets as rwidgets;
+use ratatui::Frame;
+
+use crate::config;
+use crate::db;
+use crate::keys::Action;
+use crate::ui::util;
+use crate::ui::widgets;
+use crate::ui::Mode;
+use crate::ui::NormalState;
+
+/// Marking rows, and doing things to everything marked at once.
+impl NormalState {
+    /// Marks or unmarks the selected row, then moves on to the next one.
+    pub(super) fn toggle_mark(&mut self) {
+        let Some(selected) = self.node_list_state.selected() else { return };
+        let id = self.children[selected].id;
+        if !self.marked.remove(&id) {
+            self.marked.insert(id);
+        }
+        self.go_down();
+    }
+
+    /// Starts marking a range from the selected row, or marks the range that's been started.
+    pub(super) fn toggle_visual(&mut self) {
+        match self.visual_anchor.take() {
+            Some(_) => self
+                .marked
+                .extend(self.visual_range().map(|i| self.children[i].id)),
+            None => self.visual_anchor = self.node_list_state.selected(),
+        }
+    }
+
+    pub(super) fn clear_marks(&mut self) {
+        self.marked.clear();
+        self.visual_anchor = None;
+    }
+
+    fn visual_range(&self) -> std::ops::Range<usize> {
+        match (self.visual_anchor, self.node_list_state.selected()) {
+            (Some(anchor), Some(selected)) => anchor.min(selected)..anchor.max(selected) + 1,
+            _ => 0..0,
+        }
+    }
+
+    pub(super) fn is_marking(&self) -> bool {
+        !self.marked.is_empty() || self.visual_anchor.is_some()
+    }
+
+    pub(super) fn is_marked(&self, index: usize) -> bool {
+        self.marked.contains(&self.children[index].id) || self.visual_range().contains(&index)
+    }
+
+    /// What bulk actions apply to: every marked row, or just the selected one.
+    /// Each comes with the node it's listed under, or `None` at the roots.
+    pub(super) fn targets(&self) -> Vec<(db::Node, Option<db::NodeID>)> {
+        let indices: Vec<usize> = if self.is_marking() {
+            (0..self.children.len())
+                .filter(|i| self.is_marked(*i))
+                .collect()
+        } else {
+            self.node_list_state.selected().into_iter().collect()
+        };
+
+        // The tree view can show a node more than once.
+        let mut seen = BTreeSet::new();
+        indices
+            .into_iter()
+            .filter(|i| seen.insert(self.children[*i].id))
+            .map(|i| (self.children[i].clone(), self.row_parent(i)))
+            .collect()
+    }
+
+    fn row_parent(&self, index: usize) -> Option<db::NodeID> {
+        self.tree_ancestors(index)
+            .last()
+            .or(self.node.as_ref())
+            .map(|node| node.id)
+    }
+
+    /// Completes every target, or reopens them all if they're already complete.
+    pub(super) async fn complete_targets(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let targets = self.fresh_targets(database).await?;
+        let complete = targets.iter().any(|node| node.completed.is_none());
+        let now = Utc::now();
+        self.update_targets(database, targets, |node| {
+            node.completed = match (complete, node.completed) {
+                (true, None) => Some(now),
+                (true, completed) => completed,
+                (false, _) => None,
+            };
+        })
+        .await
+    }
+
+    /// Trashes every target, or restores them all if they're already trashed.
+    pub(super) async fn trash_targets(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let targets = self.fresh_targets(database).await?;
+        let trash = targets.iter().any(|node| !node.trashed);
+        self.update_targets(database, targets, |node| node.trashed = trash)
+            .await
+    }
+
+    /// The targets as they are in the database,
+    /// so that bulk changes don't undo anyone else's.
+    async fn fresh_targets(&self, database: &db::Database) -> anyhow::Result<Vec<db::Node>> {
+        let mut nodes = Vec::new();
+        for (node, _) in self.targets().into_iter() {
+            nodes.push(database.get_node(node.id).await?);
+        }
+        Ok(nodes)
+    }
+
+    async fn update_targets(
+        &mut self,
+        database: &db::Database,
+        targets: Vec<db::Node>,
+        mut update: impl FnMut(&mut db::Node),
+    ) -> anyhow::Result<()> {
+        let changes: Vec<db::Change> = targets
+            .into_iter()
+            .map(|mut node| {
+                update(&mut node);
+                db::Change::Update(node)
+            })
+            .collect();
+        self.apply_changes(database, &changes).await
+    }
+
+    /// Applies a bulk change, then unmarks everything and shows the result.
+    /// Failures are shown rather than closing the UI, since nothing will have changed.
+    pub(super) async fn apply_changes(
+        &mut self,
+        database: &db::Database,
+        changes: &[db::Change],
+    ) -> anyhow::Result<()> {
+        if let Err(e) = database.apply(changes).await {
+            self.message = Some(e.to_string());
+            return Ok(());
+        }
+        self.clear_marks();
+        self.reload(database).await
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(super) enum PromptPurpose {
+    Tag,
+    Due,
+}
+
+/// Asks for a line of text to apply to every target of the parent.
+pub(super) struct PromptState {
+    parent: NormalState,
+    purpose: PromptPurpose,
+    input: widgets::TextAreaState,
+    /// Why the input couldn't be used, if it couldn't.
+    problem: Option<String>,
+}
+
+impl PromptState {
+    pub(super) fn new(parent: NormalState, purpose: PromptPurpose) -> Self {
+        PromptState {
+            parent,
+            purpose,
+            input: widgets::TextAreaState::single_line(""),
+            problem: None,
+        }
+    }
+
+    pub(super) fn into_parent(self) -> NormalState {
+        self.parent
+    }
+
+    pub(super) async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<Action>,
+        evt: KeyEvent,
+    ) -> anyhow::Result<Mode> {
+        match action {
+            Some(Action::Cancel) => return Ok(Mode::Normal(self.parent)),
+            Some(Action::Confirm) => return self.confirm(database).await,
+            _ => {
+                if self.input.handle_input(evt) {
+                    self.problem = None;
+                }
+            }
+        }
+        Ok(Mode::Prompt(self))
+    }
+
+    pub(super) fn handle_paste(mut self, text: String) -> Mode {
+        self.input.paste(&text);
+        Mode::Prompt(self)
+    }
+
+    async fn confirm(mut self, database: &db::Database) -> anyhow::Result<Mode> {
+        let changes = match self.purpose {
+            PromptPurpose::Tag => self.tag_changes(database).await?,
+            PromptPurpose::Due => self.due_changes(database).await?,
+        };
+        let Some(changes) = changes else { return Ok(Mode::Prompt(self)) };
+
+        let mut parent = self.parent;
+        parent.apply_changes(database, &changes).await?;
+        Ok(Mode::Normal(parent))
+    }
+
+    /// Tags are nodes like any other, which tagged nodes become children of.
+    /// They're found by title, and made as a new root if they don't exist yet.
+    async fn tag_changes(
+        &mut self,
+        database: &db::Database,
+    ) -> anyhow::Result<Option<Vec<db::Change>>> {
+        let name = self.input.text().trim();
+        if name.is_empty() {
+            self.problem = Some("Tags need a name".to_string());
+            return Ok(None);
+        }
+
+        let mut changes = Vec::new();
+        let existing = database.get_nodes_by_title(name).await?;
+        let tag = match existing.as_slice() {
+            [] => {
+                let mut tag = db::Node::new();
+                tag.title = name.to_string();
+                let id = tag.id;
+                changes.push(db::Change::Add(tag));
+                id
+            }
+            [tag] => *tag,
+            _ => {
+                self.problem = Some(format!("Several nodes are called \"{}\"", name));
+                return Ok(None);
+            }
+        };
+
+        for (node, _) in self.parent.targets().into_iter() {
+            if node.id != tag && !database.has_edge(tag, node.id).await? {
+                changes.push(db::Change::Connect(tag, node.id));
+            }
+        }
+        Ok(Some(changes))
+    }
+
+    async fn due_changes(
+        &mut self,
+        database: &db::Database,
+    ) -> anyhow::Result<Option<Vec<db::Change>>> {
+        let due = match util::parse_date_time(self.input.text()) {
+            Ok(due) => due,
+            Err(e) => {
+                self.problem = Some(e.to_string());
+                return Ok(None);
+            }
+        };
+
+        let mut changes = Vec::new();
+        for mut node in self.parent.fresh_targets(database).await?.into_iter() {
+            node.due = due;
+            changes.push(db::Change::Update(node));
+        }
+        Ok(Some(changes))
+    }
+
+    pub(super) fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let count = self.parent.targets().len();
+        let noun = if count == 1 { "node" } else { "nodes" };
+        let title = match self.purpose {
+            PromptPurpose::Tag => format!("Tag {} {}", count, noun),
+            PromptPurpose::Due => format!("Due date for {} {} (empty clears)", count, noun),
+        };
+
+        let size = f.size();
+        let width = size.width.saturating_sub(16).max(20).min(size.width);
+        let rect = layout::Rect::new(
+            size.x + (size.width - width) / 2,
+            size.y + size.height.saturating_sub(3) / 2,
+            width,
+            3.min(size.height),
+        );
+        f.render_widget(rwidgets::Clear, rect);
+
+        let mut block = rwidgets::Block::default()
+            .title(title)
+            .borders(rwidgets::Borders::all())
+            .border_style(theme.border_style());
+        if let Some(problem) = &self.problem {
+            block = block.title(Span::styled(
+                problem.clone(),
+                Style::default().fg(Color::Red),
+            ));
+        }
+        let text_area = widgets::TextArea::default().block(block);
+        f.render_stateful_widget(text_area, rect, &mut self.input);
+
+        let (x, y) = self.input.cursor_offset(rect);
+        f.set_cursor(x, y);
+    }
+}
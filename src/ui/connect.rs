@@ -0,0 +1,280 @@
+use crossterm::event::KeyEvent;
+use crossterm::event::MouseEvent;
+use ratatui::backend::Backend;
+use ratatui::layout::Constraint;
+use ratatui::layout::Direction;
+use ratatui::layout::Layout;
+use ratatui::style::Color;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
+use ratatui::widgets as rwidgets;
+use ratatui::Frame;
+
+use crate::config;
+use crate::db;
+use crate::keys::Action;
+use crate::ui::modal_area;
+use crate::ui::Mode;
+use crate::ui::NodePicker;
+use crate::ui::NormalState;
+
+#[derive(Clone, Copy)]
+enum ConnectDirection {
+    /// Make the source node a child of the target, or blocked by it.
+    ChildOf,
+    /// Make the source node a parent of the target, or a blocker of it.
+    ParentOf,
+}
+
+impl ConnectDirection {
+    fn flip(self) -> Self {
+        use ConnectDirection::*;
+        match self {
+            ChildOf => ParentOf,
+            ParentOf => ChildOf,
+        }
+    }
+
+    /// The edge between `source` and `target`, as `(parent, child)`.
+    fn edge<T>(self, source: T, target: T) -> (T, T) {
+        match self {
+            ConnectDirection::ChildOf => (target, source),
+            ConnectDirection::ParentOf => (source, target),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(super) enum ConnectPurpose {
+    /// Add an edge, leaving the others alone.
+    Connect,
+    /// Make the target a parent in place of the one each source is listed under.
+    Move,
+    /// Make the target block each source, or the other way around.
+    Block,
+}
+
+/// Picks another node to connect the selected or marked nodes to.
+/// Blocking works the same way, except the edge it adds is a blocking one.
+pub(super) struct ConnectState {
+    parent: NormalState,
+    /// Each node being connected, with the node it's listed under (if any).
+    sources: Vec<(db::Node, Option<db::NodeID>)>,
+    purpose: ConnectPurpose,
+    picker: NodePicker,
+    direction: ConnectDirection,
+    /// What confirming would do, as of the last preview.
+    changes: Vec<db::Change>,
+    /// Why the previewed edges can't be created, if they can't.
+    problem: Option<String>,
+}
+
+impl ConnectState {
+    pub(super) async fn new(
+        database: &db::Database,
+        parent: NormalState,
+        sources: Vec<(db::Node, Option<db::NodeID>)>,
+        purpose: ConnectPurpose,
+    ) -> anyhow::Result<Self> {
+        let mut state = ConnectState {
+            parent,
+            sources,
+            purpose,
+            picker: NodePicker::new(database).await?,
+            direction: ConnectDirection::ChildOf,
+            changes: vec![],
+            problem: None,
+        };
+        state.update_preview(database).await?;
+        Ok(state)
+    }
+
+    pub(super) fn into_parent(self) -> NormalState {
+        self.parent
+    }
+
+    pub(super) async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<Action>,
+        evt: KeyEvent,
+    ) -> anyhow::Result<Mode> {
+        match action {
+            Some(Action::Cancel) => return Ok(Mode::Normal(self.parent)),
+            Some(Action::Confirm) => return self.connect(database).await,
+            // Moving only ever puts nodes under the target.
+            Some(Action::SwapDirection) if self.purpose == ConnectPurpose::Connect => {
+                self.direction = self.direction.flip()
+            }
+            Some(Action::Up) => self.picker.go_up(),
+            Some(Action::Down) => self.picker.go_down(),
+            _ => self.picker.handle_input(database, evt).await?,
+        }
+        self.update_preview(database).await?;
+        Ok(Mode::Connect(self))
+    }
+
+    pub(super) async fn handle_paste(
+        mut self,
+        database: &db::Database,
+        text: String,
+    ) -> anyhow::Result<Mode> {
+        self.picker.handle_paste(database, text).await?;
+        self.update_preview(database).await?;
+        Ok(Mode::Connect(self))
+    }
+
+    pub(super) async fn handle_mouse(
+        mut self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        if self.picker.handle_mouse(evt, double_click) {
+            return self.connect(database).await;
+        }
+        self.update_preview(database).await?;
+        Ok(Mode::Connect(self))
+    }
+
+    async fn connect(mut self, database: &db::Database) -> anyhow::Result<Mode> {
+        // The preview could be stale if something else has written since.
+        self.update_preview(database).await?;
+        if self.changes.is_empty() || self.problem.is_some() {
+            return Ok(Mode::Connect(self));
+        }
+
+        // Things like duplicate edges only show up once we try to insert,
+        // so report them here rather than tearing down the whole UI.
+        if let Err(e) = database.apply(&self.changes).await {
+            self.problem = Some(e.to_string());
+            return Ok(Mode::Connect(self));
+        }
+        self.parent.clear_marks();
+        self.parent.reload(database).await?;
+        Ok(Mode::Normal(self.parent))
+    }
+
+    /// The edge that would be created when there's only one source, as `(parent, child)`.
+    fn edge(&self) -> Option<(&db::Node, &db::Node)> {
+        let target = self.picker.selected()?;
+        match self.sources.as_slice() {
+            [(source, _)] => Some(self.direction.edge(source, target)),
+            _ => None,
+        }
+    }
+
+    async fn update_preview(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        self.problem = None;
+        self.changes.clear();
+        let Some(target) = self.picker.selected().map(|node| node.id) else {
+            return Ok(());
+        };
+
+        for (source, listed_under) in self.sources.iter() {
+            let (from, to) = self.direction.edge(source.id, target);
+            if database.would_create_cycle(from, to).await? {
+                self.problem = Some(match self.sources.len() {
+                    1 => "This would create a cycle".to_string(),
+                    _ => format!("\"{}\" would create a cycle", source.title),
+                });
+                self.changes.clear();
+                return Ok(());
+            }
+
+            if self.purpose == ConnectPurpose::Block {
+                if !database.get_blockers(to).await?.contains(&from) {
+                    self.changes.push(db::Change::Block(from, to));
+                }
+                continue;
+            }
+            if let (ConnectPurpose::Move, Some(listed_under)) = (self.purpose, listed_under) {
+                if *listed_under == target {
+                    continue;
+                }
+                self.changes
+                    .push(db::Change::Disconnect(*listed_under, source.id));
+            }
+            if !database.has_edge(from, to).await? {
+                self.changes.push(db::Change::Connect(from, to));
+            }
+        }
+
+        if self.changes.is_empty() {
+            self.problem = Some(match self.purpose {
+                ConnectPurpose::Connect => "These nodes are already connected".to_string(),
+                ConnectPurpose::Move => "These nodes are already there".to_string(),
+                ConnectPurpose::Block => "These nodes are already blocked".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub(super) fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let rect = modal_area(f.size());
+        f.render_widget(rwidgets::Clear, rect);
+
+        let parts = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(5),
+                Constraint::Min(3),
+            ])
+            .split(rect);
+
+        let target = self
+            .picker
+            .selected()
+            .map(|node| node.title.as_str())
+            .unwrap_or("...");
+        let sources = match self.sources.as_slice() {
+            [(source, _)] => format!("\"{}\"", source.title),
+            sources => format!("{} nodes", sources.len()),
+        };
+        let description = match (self.purpose, self.direction, self.sources.len()) {
+            (ConnectPurpose::Move, _, _) => "under",
+            (ConnectPurpose::Block, ConnectDirection::ChildOf, _) => "blocked by",
+            (ConnectPurpose::Block, ConnectDirection::ParentOf, 1) => "a blocker of",
+            (ConnectPurpose::Block, ConnectDirection::ParentOf, _) => "blockers of",
+            (_, ConnectDirection::ChildOf, 1) => "a child of",
+            (_, ConnectDirection::ParentOf, 1) => "a parent of",
+            (_, ConnectDirection::ChildOf, _) => "children of",
+            (_, ConnectDirection::ParentOf, _) => "parents of",
+        };
+        let (verb, title) = match self.purpose {
+            ConnectPurpose::Connect => ("Make", "Connect"),
+            ConnectPurpose::Move => ("Move", "Move"),
+            ConnectPurpose::Block => ("Make", "Block"),
+        };
+        let mut lines = vec![Spans::from(format!(
+            "{} {} {} \"{}\"",
+            verb, sources, description, target,
+        ))];
+        if let Some((from, to)) = self.edge() {
+            lines.push(Spans::from(match self.purpose {
+                ConnectPurpose::Block => format!("{} blocks {}", from.title, to.title),
+                _ => format!("{} -> {}", from.title, to.title),
+            }));
+        }
+        lines.push(match &self.problem {
+            Some(problem) => Spans::from(Span::styled(
+                problem.clone(),
+                Style::default().fg(Color::Red),
+            )),
+            None => Spans::from("F1: list keys"),
+        });
+        let preview = rwidgets::Paragraph::new(lines).block(
+            rwidgets::Block::default()
+                .title(title)
+                .borders(rwidgets::Borders::all())
+                .border_style(theme.border_style()),
+        );
+        f.render_widget(preview, parts[1]);
+
+        self.picker.render(f, parts[0], parts[2], theme);
+    }
+}
@@ -0,0 +1,308 @@
+use chrono::Utc;
+use crossterm::event::KeyEvent;
+use crossterm::event::MouseEvent;
+use ratatui::backend::Backend;
+use ratatui::layout::Constraint;
+use ratatui::layout::Direction;
+use ratatui::layout::Layout;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
+use ratatui::widgets as rwidgets;
+use ratatui::Frame;
+
+use crate::config;
+use crate::db;
+use crate::keys::Action;
+use crate::ui::keymap;
+use crate::ui::modal_area;
+use crate::ui::util;
+use crate::ui::widgets;
+use crate::ui::Mode;
+use crate::ui::NodePicker;
+use crate::ui::NormalState;
+
+enum InboxStep {
+    /// Deciding what to do with the current item.
+    Choose,
+    /// Picking the project to move it under.
+    Move(NodePicker),
+    /// Typing when to do it.
+    Schedule(widgets::TextAreaState),
+}
+
+/// Steps through what's been captured into the inbox,
+/// deciding what to do with each item in turn.
+pub(super) struct InboxState {
+    parent: NormalState,
+    inbox: db::NodeID,
+    /// The items which haven't been dealt with yet.
+    items: Vec<db::Node>,
+    /// Which item is being processed.
+    current: usize,
+    step: InboxStep,
+    /// Why the last choice couldn't be made, if it couldn't.
+    problem: Option<String>,
+}
+
+impl InboxState {
+    pub(super) async fn new(database: &db::Database, parent: NormalState) -> anyhow::Result<Self> {
+        Ok(InboxState {
+            parent,
+            inbox: database.inbox().await?,
+            items: database.get_inbox_items().await?,
+            current: 0,
+            step: InboxStep::Choose,
+            problem: None,
+        })
+    }
+
+    pub(super) fn into_parent(self) -> NormalState {
+        self.parent
+    }
+
+    /// The moving and scheduling steps work like the find and prompt modes,
+    /// so they share their keys.
+    pub(super) fn key_context(&self) -> keymap::Context {
+        match self.step {
+            InboxStep::Choose => keymap::Context::Inbox,
+            InboxStep::Move(_) => keymap::Context::Find,
+            InboxStep::Schedule(_) => keymap::Context::Prompt,
+        }
+    }
+
+    pub(super) async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<Action>,
+        evt: KeyEvent,
+    ) -> anyhow::Result<Mode> {
+        if action == Some(Action::Cancel) {
+            if let InboxStep::Choose = self.step {
+                // Processing will have changed what the parent is showing.
+                self.parent.reload(database).await?;
+                return Ok(Mode::Normal(self.parent));
+            }
+            self.step = InboxStep::Choose;
+            self.problem = None;
+            return Ok(Mode::Inbox(self));
+        }
+
+        match &mut self.step {
+            InboxStep::Choose => self.handle_choice(database, action).await?,
+            InboxStep::Move(picker) => match action {
+                Some(Action::Confirm) => self.move_current(database).await?,
+                Some(Action::Up) => picker.go_up(),
+                Some(Action::Down) => picker.go_down(),
+                _ => picker.handle_input(database, evt).await?,
+            },
+            InboxStep::Schedule(input) => match action {
+                Some(Action::Confirm) => self.schedule_current(database).await?,
+                _ => {
+                    if input.handle_input(evt) {
+                        self.problem = None;
+                    }
+                }
+            },
+        }
+        Ok(Mode::Inbox(self))
+    }
+
+    pub(super) async fn handle_paste(
+        mut self,
+        database: &db::Database,
+        text: String,
+    ) -> anyhow::Result<Mode> {
+        match &mut self.step {
+            InboxStep::Choose => {}
+            InboxStep::Move(picker) => picker.handle_paste(database, text).await?,
+            InboxStep::Schedule(input) => input.paste(&text),
+        }
+        Ok(Mode::Inbox(self))
+    }
+
+    pub(super) async fn handle_mouse(
+        mut self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        if let InboxStep::Move(picker) = &mut self.step {
+            if picker.handle_mouse(evt, double_click) {
+                self.move_current(database).await?;
+            }
+        }
+        Ok(Mode::Inbox(self))
+    }
+
+    async fn handle_choice(
+        &mut self,
+        database: &db::Database,
+        action: Option<Action>,
+    ) -> anyhow::Result<()> {
+        let Some(item) = self.items.get(self.current) else { return Ok(()) };
+        match action {
+            Some(Action::Up) => self.current = self.current.saturating_sub(1),
+            Some(Action::Down) => self.current = (self.current + 1).min(self.items.len() - 1),
+            Some(Action::Complete) => {
+                // Start from what's in the database so this doesn't undo anyone else's changes.
+                let mut item = database.get_node(item.id).await?;
+                item.completed = Some(Utc::now());
+                self.process(database, &[db::Change::Update(item)]).await?;
+            }
+            Some(Action::Trash) => {
+                let mut item = database.get_node(item.id).await?;
+                item.trashed = true;
+                self.process(database, &[db::Change::Update(item)]).await?;
+            }
+            Some(Action::Move) => self.step = InboxStep::Move(NodePicker::new(database).await?),
+            Some(Action::Schedule) => {
+                self.step = InboxStep::Schedule(widgets::TextAreaState::single_line(""))
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Takes the current item out of the inbox and puts it under the picked node.
+    async fn move_current(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let InboxStep::Move(picker) = &self.step else { return Ok(()) };
+        let Some(target) = picker.selected().map(|node| node.id) else { return Ok(()) };
+        let item = self.items[self.current].id;
+        if target == self.inbox {
+            self.problem = Some("That's the inbox".to_string());
+            return Ok(());
+        }
+        if database.would_create_cycle(target, item).await? {
+            self.problem = Some("This would create a cycle".to_string());
+            return Ok(());
+        }
+
+        let mut changes = vec![db::Change::Disconnect(self.inbox, item)];
+        if !database.has_edge(target, item).await? {
+            changes.push(db::Change::Connect(target, item));
+        }
+        self.process(database, &changes).await
+    }
+
+    /// Scheduled items stay in the inbox, but don't need processing any more.
+    async fn schedule_current(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let InboxStep::Schedule(input) = &self.step else { return Ok(()) };
+        let scheduled = match util::parse_date_time(input.text()) {
+            Ok(Some(scheduled)) => scheduled,
+            Ok(None) => {
+                self.problem = Some("Scheduling needs a date".to_string());
+                return Ok(());
+            }
+            Err(e) => {
+                self.problem = Some(e.to_string());
+                return Ok(());
+            }
+        };
+
+        let mut item = database.get_node(self.items[self.current].id).await?;
+        item.scheduled = Some(scheduled);
+        self.process(database, &[db::Change::Update(item)]).await
+    }
+
+    /// Applies what was chosen for the current item, and moves on to the next one.
+    async fn process(
+        &mut self,
+        database: &db::Database,
+        changes: &[db::Change],
+    ) -> anyhow::Result<()> {
+        if let Err(e) = database.apply(changes).await {
+            self.problem = Some(e.to_string());
+            return Ok(());
+        }
+        self.items.remove(self.current);
+        self.current = self.current.min(self.items.len().saturating_sub(1));
+        self.step = InboxStep::Choose;
+        self.problem = None;
+        Ok(())
+    }
+
+    pub(super) fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let rect = modal_area(f.size());
+        f.render_widget(rwidgets::Clear, rect);
+
+        let title = match self.items.len() {
+            0 => "Inbox".to_string(),
+            count => format!("Inbox ({} of {})", self.current + 1, count),
+        };
+        let Some(item) = self.items.get(self.current) else {
+            let empty = rwidgets::Paragraph::new("Nothing left to process").block(
+                rwidgets::Block::default()
+                    .title(title)
+                    .borders(rwidgets::Borders::all())
+                    .border_style(theme.border_style()),
+            );
+            f.render_widget(empty, rect);
+            return;
+        };
+
+        let mut lines = vec![Spans::from(Span::styled(
+            item.title.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ))];
+        if let Some(due) = item.due {
+            lines.push(Spans::from(format!("due {}", util::format_date_time(due))));
+        }
+        if !item.description.is_empty() {
+            lines.push(Spans::from(""));
+            lines.extend(item.description.lines().map(Spans::from));
+        }
+        lines.push(Spans::from(""));
+        lines.push(match &self.problem {
+            Some(problem) => Spans::from(Span::styled(
+                problem.clone(),
+                Style::default().fg(Color::Red),
+            )),
+            None => Spans::from("Do it, move it, schedule it or trash it. F1: list keys"),
+        });
+        let details = rwidgets::Paragraph::new(lines)
+            .wrap(rwidgets::Wrap { trim: false })
+            .block(
+                rwidgets::Block::default()
+                    .title(title)
+                    .borders(rwidgets::Borders::all())
+                    .border_style(theme.border_style()),
+            );
+
+        match &mut self.step {
+            InboxStep::Choose => f.render_widget(details, rect),
+            InboxStep::Move(picker) => {
+                let parts = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([
+                        Constraint::Length(3),
+                        Constraint::Length(8),
+                        Constraint::Min(3),
+                    ])
+                    .split(rect);
+                f.render_widget(details, parts[1]);
+                picker.render(f, parts[0], parts[2], theme);
+            }
+            InboxStep::Schedule(input) => {
+                let parts = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(3), Constraint::Min(0)])
+                    .split(rect);
+                f.render_widget(details, parts[1]);
+                let text_area = widgets::TextArea::default().block(
+                    rwidgets::Block::default()
+                        .title("Schedule for")
+                        .borders(rwidgets::Borders::all())
+                        .border_style(theme.border_style()),
+                );
+                f.render_stateful_widget(text_area, parts[0], input);
+                let (x, y) = input.cursor_offset(parts[0]);
+                f.set_cursor(x, y);
+            }
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use std::collections::BTreeMap;
+
+use crossterm::event::KeyEvent;
+
+use crate::keys::Action;
+use crate::keys::Bindings;
+use crate::keys::Key;
+use crate::keys::KeysConfig;
+use crate::keys::Preset;
+
+/// Which bindings are active, depending on what the UI is doing.
+/// Global bindings apply everywhere, underneath the others.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Context {
+    Global,
+    List,
+    Editor,
+    Add,
+    Find,
+    Connect,
+    Next,
+    Parents,
+    Agenda,
+    Prompt,
+    Inbox,
+    Review,
+}
+
+impl Context {
+    pub fn name(self) -> &'static str {
+        use Context::*;
+        match self {
+            Global => "global",
+            List => "list",
+            Editor => "editor",
+            Add => "add",
+            Find => "find",
+            Connect => "connect",
+            Next => "next",
+            Parents => "parents",
+            Agenda => "agenda",
+            Prompt => "prompt",
+            Inbox => "inbox",
+            Review => "review",
+        }
+    }
+}
+
+/// Pairs each context with the keys the config binds in it.
+fn overrides(config: &KeysConfig) -> [(Context, &BTreeMap<Action, Bindings>); 12] {
+    [
+        (Context::Global, &config.global),
+        (Context::List, &config.list),
+        (Context::Editor, &config.editor),
+        (Context::Add, &config.add),
+        (Context::Find, &config.find),
+        (Context::Connect, &config.connect),
+        (Context::Next, &config.next),
+        (Context::Parents, &config.parents),
+        (Context::Agenda, &config.agenda),
+        (Context::Prompt, &config.prompt),
+        (Context::Inbox, &config.inbox),
+        (Context::Review, &config.review),
+    ]
+}
+
+/// Maps key presses to actions, depending on the context they happen in.
+pub struct Keymap {
+    /// Kept in the order they were bound, so that help lists them sensibly.
+    bindings: Vec<(Context, Action, Vec<Key>)>,
+}
+
+impl Keymap {
+    pub fn new(config: &KeysConfig) -> Self {
+        let mut keymap = Self::preset(config.preset);
+        for (context, overrides) in overrides(config).into_iter() {
+            for (action, bindings) in overrides.iter() {
+                keymap.rebind(context, *action, bindings.keys());
+            }
+        }
+        keymap
+    }
+
+    /// Finds the action `evt` triggers in `context`, if any.
+    pub fn action(&self, context: Context, evt: KeyEvent) -> Option<Action> {
+        let key = Key::from(evt);
+        [context, Context::Global].into_iter().find_map(|context| {
+            self.bindings
+                .iter()
+                .find(|(bound_context, _, keys)| *bound_context == context && keys.contains(&key))
+                .map(|(_, action, _)| *action)
+        })
+    }
+
+    /// Everything bound in `context`, followed by the global bindings.
+    pub fn bindings(&self, context: Context) -> Vec<(Action, &[Key])> {
+        [context, Context::Global]
+            .into_iter()
+            .flat_map(|context| {
+                self.bindings
+                    .iter()
+                    .filter(move |(bound_context, _, keys)| {
+                        *bound_context == context && !keys.is_empty()
+                    })
+                    .map(|(_, action, keys)| (*action, keys.as_slice()))
+            })
+            .collect()
+    }
+
+    fn preset(preset: Preset) -> Self {
+        use Action::*;
+
+        let mut keymap = Keymap { bindings: vec![] };
+        keymap.bind(Context::Global, Quit, &["ctrl+c"]);
+        keymap.bind(Context::Global, Help, &["f1"]);
+        keymap.bind(Context::Global, Suspend, &["ctrl+z"]);
+
+        keymap.bind(Context::List, Up, &["up"]);
+        keymap.bind(Context::List, Down, &["down"]);
+        keymap.bind(Context::List, Back, &["left"]);
+        keymap.bind(Context::List, Open, &["right"]);
+        keymap.bind(Context::List, Descend, &["enter"]);
+        keymap.bind(Context::List, NextField, &["tab"]);
+        keymap.bind(Context::List, PrevField, &["shift+tab"]);
+        keymap.bind(Context::List, Add, &["a"]);
+        keymap.bind(Context::List, Find, &["f"]);
+        keymap.bind(Context::List, Connect, &["c"]);
+        keymap.bind(Context::List, Next, &["n"]);
+        keymap.bind(Context::List, Parents, &["p"]);
+        keymap.bind(Context::List, Complete, &["x"]);
+        keymap.bind(Context::List, Trash, &["d"]);
+        keymap.bind(Context::List, ToggleView, &["t"]);
+        keymap.bind(Context::List, Edit, &["e"]);
+        keymap.bind(Context::List, Agenda, &["s"]);
+        keymap.bind(Context::List, Mark, &["space"]);
+        keymap.bind(Context::List, Visual, &["v"]);
+        keymap.bind(Context::List, ClearMarks, &["esc"]);
+        keymap.bind(Context::List, Move, &["m"]);
+        keymap.bind(Context::List, Tag, &["T"]);
+        keymap.bind(Context::List, SetDue, &["D"]);
+        keymap.bind(Context::List, Inbox, &["i"]);
+        keymap.bind(Context::List, Review, &["R"]);
+        keymap.bind(Context::List, Block, &["b"]);
+        keymap.bind(Context::List, Help, &["?"]);
+
+        keymap.bind(Context::Editor, NextField, &["tab"]);
+        keymap.bind(Context::Editor, PrevField, &["shift+tab"]);
+
+        keymap.bind(Context::Add, Cancel, &["esc", "ctrl+g"]);
+        keymap.bind(Context::Add, Confirm, &["ctrl+f"]);
+        keymap.bind(Context::Add, NextField, &["tab"]);
+        keymap.bind(Context::Add, PrevField, &["shift+tab"]);
+
+        for context in [Context::Find, Context::Connect, Context::Parents] {
+            keymap.bind(context, Cancel, &["esc", "ctrl+g"]);
+            keymap.bind(context, Confirm, &["ctrl+f"]);
+            keymap.bind(context, Up, &["up"]);
+            keymap.bind(context, Down, &["down"]);
+        }
+        keymap.bind(Context::Connect, SwapDirection, &["tab", "shift+tab"]);
+        keymap.bind(Context::Parents, Confirm, &["enter"]);
+        keymap.bind(Context::Parents, Help, &["?"]);
+
+        keymap.bind(Context::Next, Cancel, &["esc", "ctrl+g"]);
+        keymap.bind(Context::Next, Confirm, &["enter"]);
+        keymap.bind(Context::Next, Up, &["up"]);
+        keymap.bind(Context::Next, Down, &["down"]);
+        keymap.bind(Context::Next, Complete, &["x"]);
+        keymap.bind(Context::Next, Help, &["?"]);
+
+        keymap.bind(Context::Agenda, Cancel, &["esc", "ctrl+g"]);
+        keymap.bind(Context::Agenda, Confirm, &["enter"]);
+        keymap.bind(Context::Agenda, Up, &["up"]);
+        keymap.bind(Context::Agenda, Down, &["down"]);
+        keymap.bind(Context::Agenda, Later, &["+"]);
+        keymap.bind(Context::Agenda, Earlier, &["-"]);
+        keymap.bind(Context::Agenda, Help, &["?"]);
+
+        keymap.bind(Context::Prompt, Cancel, &["esc", "ctrl+g"]);
+        keymap.bind(Context::Prompt, Confirm, &["enter"]);
+
+        keymap.bind(Context::Inbox, Cancel, &["esc", "ctrl+g"]);
+        keymap.bind(Context::Inbox, Up, &["up"]);
+        keymap.bind(Context::Inbox, Down, &["down"]);
+        keymap.bind(Context::Inbox, Complete, &["x"]);
+        keymap.bind(Context::Inbox, Move, &["m"]);
+        keymap.bind(Context::Inbox, Schedule, &["s"]);
+        keymap.bind(Context::Inbox, Trash, &["d"]);
+        keymap.bind(Context::Inbox, Help, &["?"]);
+
+        keymap.bind(Context::Review, Cancel, &["esc", "ctrl+g"]);
+        keymap.bind(Context::Review, Confirm, &["enter"]);
+        keymap.bind(Context::Review, Up, &["up"]);
+        keymap.bind(Context::Review, Down, &["down"]);
+        keymap.bind(Context::Review, Complete, &["x"]);
+        keymap.bind(Context::Review, Trash, &["d"]);
+        keymap.bind(Context::Review, MarkReviewed, &["r"]);
+        keymap.bind(Context::Review, Help, &["?"]);
+
+        match preset {
+            Preset::Default => {}
+            Preset::Vim => {
+                keymap.bind(Context::List, Up, &["k"]);
+                keymap.bind(Context::List, Down, &["j"]);
+                keymap.bind(Context::List, Back, &["h"]);
+                keymap.bind(Context::List, Open, &["l"]);
+                keymap.bind(Context::List, Find, &["/"]);
+                for context in [
+                    Context::Next,
+                    Context::Parents,
+                    Context::Agenda,
+                    Context::Inbox,
+                    Context::Review,
+                ] {
+                    keymap.bind(context, Up, &["k"]);
+                    keymap.bind(context, Down, &["j"]);
+                }
+                // These have a text box, so letters are taken.
+                for context in [Context::Find, Context::Connect] {
+                    keymap.bind(context, Up, &["ctrl+p"]);
+                    keymap.bind(context, Down, &["ctrl+n"]);
+                }
+            }
+            Preset::Emacs => {
+                keymap.bind(Context::List, Up, &["ctrl+p"]);
+                keymap.bind(Context::List, Down, &["ctrl+n"]);
+                keymap.bind(Context::List, Back, &["ctrl+b"]);
+                keymap.bind(Context::List, Open, &["ctrl+f"]);
+                for context in [
+                    Context::Find,
+                    Context::Connect,
+                    Context::Next,
+                    Context::Parents,
+                    Context::Agenda,
+                    Context::Inbox,
+                    Context::Review,
+                ] {
+                    keymap.bind(context, Up, &["ctrl+p"]);
+                    keymap.bind(context, Down, &["ctrl+n"]);
+                }
+                for context in [Context::Add, Context::Find, Context::Connect] {
+                    keymap.bind(context, Confirm, &["enter"]);
+                }
+            }
+        }
+        keymap
+    }
+
+    /// Adds to the keys bound to `action`.
+    fn bind(&mut self, context: Context, action: Action, keys: &[&str]) {
+        let keys = keys
+            .iter()
+            .map(|key| key.parse().expect("Built-in bindings should parse"));
+        match self.entry(context, action) {
+            Some(bound) => bound.extend(keys),
+            None => self.bindings.push((context, action, keys.collect())),
+        }
+    }
+
+    /// Replaces the keys bound to `action`.
+    fn rebind(&mut self, context: Context, action: Action, keys: Vec<Key>) {
+        match self.entry(context, action) {
+            Some(bound) => *bound = keys,
+            None => self.bindings.push((context, action, keys)),
+        }
+    }
+
+    fn entry(&mut self, context: Context, action: Action) -> Option<&mut Vec<Key>> {
+        self.bindings
+            .iter_mut()
+            .find(|(bound_context, bound_action, _)| {
+                *bound_context == context && *bound_action == action
+            })
+            .map(|(_, _, keys)| keys)
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use crossterm::event::KeyCode;
+    use crossterm::event::KeyModifiers;
+
+    use super::*;
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_action__falls_back_to_global() {
+        let keymap = Keymap::new(&KeysConfig::default());
+        let ctrl_c = event(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert_eq!(keymap.action(Context::Find, ctrl_c), Some(Action::Quit));
+        // Plain letters in a text box are just typing.
+        let a = event(KeyCode::Char('a'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(Context::Find, a), None);
+        assert_eq!(keymap.action(Context::List, a), Some(Action::Add));
+    }
+
+    #[test]
+    fn test_action__ignores_shift_on_characters() {
+        let keymap = Keymap::new(&KeysConfig::default());
+        let question_mark = event(KeyCode::Char('?'), KeyModifiers::SHIFT);
+        assert_eq!(
+            keymap.action(Context::List, question_mark),
+            Some(Action::Help)
+        );
+    }
+
+    #[test]
+    fn test_new__overrides_replace_preset() {
+        let config: KeysConfig =
+            toml::from_str("preset = \"vim\"\n[list]\nadd = \"o\"\ndown = [\"j\", \"ctrl+j\"]\n")
+                .unwrap();
+        let keymap = Keymap::new(&config);
+        let press = |c| event(KeyCode::Char(c), KeyModifiers::NONE);
+        assert_eq!(keymap.action(Context::List, press('o')), Some(Action::Add));
+        assert_eq!(keymap.action(Context::List, press('a')), None);
+        assert_eq!(keymap.action(Context::List, press('k')), Some(Action::Up));
+        let down = event(KeyCode::Down, KeyModifiers::NONE);
+        assert_eq!(keymap.action(Context::List, down), None);
+    }
+}
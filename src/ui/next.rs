@@ -0,0 +1,163 @@
+use chrono::Utc;
+use crossterm::event::MouseEvent;
+use ratatui::backend::Backend;
+use ratatui::style::Color;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
+use ratatui::widgets as rwidgets;
+use ratatui::Frame;
+
+use crate::config;
+use crate::db;
+use crate::keys::Action;
+use crate::ui::modal_area;
+use crate::ui::util;
+use crate::ui::widgets;
+use crate::ui::Mode;
+use crate::ui::NormalState;
+
+/// Lists the actionable leaves beneath the node being viewed in `parent`,
+/// or beneath every root when viewing the roots.
+pub(super) struct NextState {
+    parent: NormalState,
+    actions: Vec<db::NextAction>,
+    action_list: widgets::SelectableList,
+}
+
+impl NextState {
+    pub(super) async fn new(database: &db::Database, parent: NormalState) -> anyhow::Result<Self> {
+        let mut state = NextState {
+            parent,
+            actions: vec![],
+            action_list: widgets::SelectableList::default(),
+        };
+        state.refresh(database).await?;
+        Ok(state)
+    }
+
+    pub(super) fn into_parent(self) -> NormalState {
+        self.parent
+    }
+
+    pub(super) async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<Action>,
+    ) -> anyhow::Result<Mode> {
+        match action {
+            Some(Action::Cancel) => {
+                // Completing things here may have changed what the parent is showing.
+                self.parent.refresh(database).await?;
+                return Ok(Mode::Normal(self.parent));
+            }
+            Some(Action::Up) => self.action_list.go_up(),
+            Some(Action::Down) => self.action_list.go_down(self.actions.len()),
+            Some(Action::Complete) => self.complete_selected(database).await?,
+            Some(Action::Confirm) => return self.jump_to_selected(database).await,
+            _ => {}
+        }
+        Ok(Mode::Next(self))
+    }
+
+    pub(super) async fn handle_mouse(
+        mut self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        let len = self.actions.len();
+        if self.action_list.handle_mouse(evt, double_click, len, Some) {
+            return self.jump_to_selected(database).await;
+        }
+        Ok(Mode::Next(self))
+    }
+
+    async fn complete_selected(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let Some(selected) = self.action_list.selected() else { return Ok(()) };
+        // Start from what's in the database so this doesn't undo anyone else's changes.
+        let mut node = database.get_node(self.actions[selected].node.id).await?;
+        node.completed = Some(Utc::now());
+        database.update(&node).await?;
+
+        self.refresh(database).await?;
+        // Stay roughly where we were rather than jumping back to the top.
+        if !self.actions.is_empty() {
+            self.action_list
+                .select(Some(selected.min(self.actions.len() - 1)));
+        }
+        Ok(())
+    }
+
+    async fn jump_to_selected(self, database: &db::Database) -> anyhow::Result<Mode> {
+        let Some(selected) = self.action_list.selected() else {
+            return Ok(Mode::Next(self));
+        };
+        let action = &self.actions[selected];
+
+        // Action paths start at the node being viewed,
+        // so they extend the parent's own path.
+        let mut path = self.parent.node_path.clone();
+        path.extend(action.path.iter().cloned());
+
+        let mut parent = self.parent;
+        parent.jump_to(database, path, action.node.id).await?;
+        Ok(Mode::Normal(parent))
+    }
+
+    async fn refresh(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let roots = match &self.parent.node {
+            Some(node) => vec![node.id],
+            None => database.get_roots().await?,
+        };
+        self.actions = database.next_actions(&roots).await?;
+        self.action_list.select_first(self.actions.len());
+        Ok(())
+    }
+
+    pub(super) fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let rect = modal_area(f.size());
+        f.render_widget(rwidgets::Clear, rect);
+
+        let now = Utc::now();
+        let items: Vec<rwidgets::ListItem> = self
+            .actions
+            .iter()
+            .map(|action| {
+                let mut title = vec![Span::raw(action.node.title.clone())];
+                if let Some(due) = action.node.due {
+                    let style = if due < now {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default()
+                    };
+                    let due = util::format_date_time(due);
+                    title.push(Span::styled(format!("  due {}", due), style));
+                }
+
+                let path: Vec<&str> = action.path.iter().map(|node| node.title.as_str()).collect();
+                let path = Span::styled(
+                    format!("  {}", path.join(" / ")),
+                    Style::default().fg(Color::DarkGray),
+                );
+                rwidgets::ListItem::new(vec![Spans::from(title), Spans::from(path)])
+            })
+            .collect();
+
+        let list = rwidgets::List::new(items)
+            .block(
+                rwidgets::Block::default()
+                    .title("Next")
+                    .borders(rwidgets::Borders::all())
+                    .border_style(theme.border_style()),
+            )
+            .highlight_style(theme.highlight_style())
+            .highlight_symbol(">>");
+        // Each item is a title with its path underneath.
+        let selected = self.action_list.selected();
+        self.action_list
+            .render(f, list, rect, vec![2; self.actions.len()], selected);
+    }
+}
@@ -0,0 +1,136 @@
+use crossterm::event::MouseEvent;
+use ratatui::backend::Backend;
+use ratatui::style::Color;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
+use ratatui::widgets as rwidgets;
+use ratatui::Frame;
+
+use crate::config;
+use crate::db;
+use crate::keys::Action;
+use crate::ui::modal_area;
+use crate::ui::Mode;
+use crate::ui::widgets;
+use crate::ui::NormalState;
+
+/// Lists every parent of a node, so that nodes which live in several places
+/// can be followed to any of them rather than just the one we came from.
+pub(super) struct ParentsState {
+    parent: NormalState,
+    child: db::Node,
+    /// Each parent along with its own ancestors, ordered from a root down.
+    parents: Vec<(db::Node, Vec<db::Node>)>,
+    parent_list: widgets::SelectableList,
+}
+
+impl ParentsState {
+    pub(super) async fn new(
+        database: &db::Database,
+        parent: NormalState,
+        child: db::Node,
+    ) -> anyhow::Result<Self> {
+        let mut parents = Vec::new();
+        for id in database.get_parents(child.id).await?.into_iter() {
+            parents.push((database.get_node(id).await?, database.get_path(id).await?));
+        }
+
+        let mut parent_list = widgets::SelectableList::default();
+        parent_list.select_first(parents.len());
+        Ok(ParentsState {
+            parent,
+            child,
+            parents,
+            parent_list,
+        })
+    }
+
+    pub(super) fn into_parent(self) -> NormalState {
+        self.parent
+    }
+
+    pub(super) async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<Action>,
+    ) -> anyhow::Result<Mode> {
+        match action {
+            Some(Action::Cancel) => return Ok(Mode::Normal(self.parent)),
+            Some(Action::Confirm) => return self.choose(database).await,
+            Some(Action::Up) => self.parent_list.go_up(),
+            Some(Action::Down) => self.parent_list.go_down(self.parents.len()),
+            _ => {}
+        }
+        Ok(Mode::Parents(self))
+    }
+
+    pub(super) async fn handle_mouse(
+        mut self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        let len = self.parents.len();
+        if self.parent_list.handle_mouse(evt, double_click, len, Some) {
+            return self.choose(database).await;
+        }
+        Ok(Mode::Parents(self))
+    }
+
+    /// Views the selected parent, with the node we started from selected among its children.
+    async fn choose(self, database: &db::Database) -> anyhow::Result<Mode> {
+        let Some(selected) = self.parent_list.selected() else {
+            return Ok(Mode::Parents(self));
+        };
+        let (node, ancestors) = &self.parents[selected];
+        let mut path = ancestors.clone();
+        path.push(node.clone());
+
+        let mut parent = self.parent;
+        parent.jump_to(database, path, self.child.id).await?;
+        Ok(Mode::Normal(parent))
+    }
+
+    pub(super) fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let rect = modal_area(f.size());
+        f.render_widget(rwidgets::Clear, rect);
+
+        let items: Vec<rwidgets::ListItem> = if self.parents.is_empty() {
+            vec![rwidgets::ListItem::new("This is a root node")]
+        } else {
+            self.parents
+                .iter()
+                .map(|(node, ancestors)| {
+                    let mut path: Vec<&str> =
+                        ancestors.iter().map(|node| node.title.as_str()).collect();
+                    path.insert(0, "Root");
+                    let path = Span::styled(
+                        format!("  {}", path.join(" / ")),
+                        Style::default().fg(Color::DarkGray),
+                    );
+                    rwidgets::ListItem::new(vec![
+                        Spans::from(node.title.as_str()),
+                        Spans::from(path),
+                    ])
+                })
+                .collect()
+        };
+
+        let list = rwidgets::List::new(items)
+            .block(
+                rwidgets::Block::default()
+                    .title(format!("Parents of \"{}\"", self.child.title))
+                    .borders(rwidgets::Borders::all())
+                    .border_style(theme.border_style()),
+            )
+            .highlight_style(theme.highlight_style())
+            .highlight_symbol(">>");
+        // Each item is a title with its path underneath.
+        let selected = self.parent_list.selected();
+        self.parent_list
+            .render(f, list, rect, vec![2; self.parents.len()], selected);
+    }
+}
@@ -0,0 +1,235 @@
+use chrono::Utc;
+use crossterm::event::MouseEvent;
+use ratatui::backend::Backend;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
+use ratatui::widgets as rwidgets;
+use ratatui::Frame;
+
+use crate::config;
+use crate::db;
+use crate::keys::Action;
+use crate::review;
+use crate::ui::modal_area;
+use crate::ui::util;
+use crate::ui::widgets;
+use crate::ui::Mode;
+use crate::ui::NormalState;
+
+/// One line of the review list.
+enum ReviewRow {
+    Header(review::Category),
+    /// An index into `ReviewState::items`.
+    Item(usize),
+}
+
+/// Walks through each category of a weekly review, acting on nodes as they come up.
+/// Items stay put once they've been dealt with, so that the list doesn't shift underfoot.
+pub(super) struct ReviewState {
+    parent: NormalState,
+    items: Vec<review::ReviewItem>,
+    rows: Vec<ReviewRow>,
+    /// Selects among `items`, though it's `rows` which get drawn.
+    item_list: widgets::SelectableList,
+}
+
+impl ReviewState {
+    pub(super) async fn new(database: &db::Database, parent: NormalState) -> anyhow::Result<Self> {
+        let items = review::gather(database, Utc::now()).await?;
+        let mut rows = Vec::new();
+        let mut last_category = None;
+        for (i, item) in items.iter().enumerate() {
+            if last_category != Some(item.category) {
+                rows.push(ReviewRow::Header(item.category));
+                last_category = Some(item.category);
+            }
+            rows.push(ReviewRow::Item(i));
+        }
+
+        let mut item_list = widgets::SelectableList::default();
+        item_list.select_first(items.len());
+        Ok(ReviewState {
+            parent,
+            items,
+            rows,
+            item_list,
+        })
+    }
+
+    pub(super) fn into_parent(self) -> NormalState {
+        self.parent
+    }
+
+    pub(super) async fn handle_input(
+        mut self,
+        database: &db::Database,
+        action: Option<Action>,
+    ) -> anyhow::Result<Mode> {
+        match action {
+            Some(Action::Cancel) => {
+                // Acting on nodes here may have changed what the parent is showing.
+                self.parent.reload(database).await?;
+                return Ok(Mode::Normal(self.parent));
+            }
+            Some(Action::Up) => self.item_list.go_up(),
+            Some(Action::Down) => self.item_list.go_down(self.items.len()),
+            Some(Action::Complete) => {
+                self.update_selected(database, |node| {
+                    node.completed = match node.completed {
+                        None => Some(Utc::now()),
+                        Some(_) => None,
+                    };
+                })
+                .await?
+            }
+            Some(Action::Trash) => {
+                self.update_selected(database, |node| node.trashed = !node.trashed)
+                    .await?
+            }
+            Some(Action::MarkReviewed) => self.mark_selected_reviewed(database).await?,
+            Some(Action::Confirm) => return self.jump_to_selected(database).await,
+            _ => {}
+        }
+        Ok(Mode::Review(self))
+    }
+
+    pub(super) async fn handle_mouse(
+        mut self,
+        database: &db::Database,
+        evt: MouseEvent,
+        double_click: bool,
+    ) -> anyhow::Result<Mode> {
+        let rows = &self.rows;
+        let item_at = |row: usize| match rows[row] {
+            ReviewRow::Item(index) => Some(index),
+            ReviewRow::Header(_) => None,
+        };
+        if self
+            .item_list
+            .handle_mouse(evt, double_click, self.items.len(), item_at)
+        {
+            return self.jump_to_selected(database).await;
+        }
+        Ok(Mode::Review(self))
+    }
+
+    /// Updates the selected node, along with anywhere else it's listed.
+    async fn update_selected(
+        &mut self,
+        database: &db::Database,
+        update: impl FnOnce(&mut db::Node),
+    ) -> anyhow::Result<()> {
+        let Some(selected) = self.item_list.selected() else { return Ok(()) };
+        // Start from what's in the database so this doesn't undo anyone else's changes.
+        let mut node = database.get_node(self.items[selected].node.id).await?;
+        update(&mut node);
+        database.update(&node).await?;
+        for item in self.items.iter_mut().filter(|item| item.node.id == node.id) {
+            item.node = node.clone();
+        }
+        Ok(())
+    }
+
+    async fn mark_selected_reviewed(&mut self, database: &db::Database) -> anyhow::Result<()> {
+        let Some(selected) = self.item_list.selected() else { return Ok(()) };
+        let id = self.items[selected].node.id;
+        let now = Utc::now();
+        database.set_reviewed(id, now).await?;
+        for item in self.items.iter_mut().filter(|item| item.node.id == id) {
+            item.reviewed = Some(now);
+        }
+        self.item_list.go_down(self.items.len());
+        Ok(())
+    }
+
+    async fn jump_to_selected(self, database: &db::Database) -> anyhow::Result<Mode> {
+        let Some(selected) = self.item_list.selected() else {
+            return Ok(Mode::Review(self));
+        };
+        let item = &self.items[selected];
+        let path = item.path.clone();
+        let id = item.node.id;
+
+        let mut parent = self.parent;
+        parent.jump_to(database, path, id).await?;
+        Ok(Mode::Normal(parent))
+    }
+
+    pub(super) fn render(&mut self, f: &mut Frame<impl Backend>, theme: &config::Theme) {
+        self.parent.render(f, theme);
+
+        let rect = modal_area(f.size());
+        f.render_widget(rwidgets::Clear, rect);
+
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut heights = Vec::new();
+        let items: Vec<rwidgets::ListItem> = self
+            .rows
+            .iter()
+            .map(|row| match row {
+                ReviewRow::Header(category) => {
+                    heights.push(1);
+                    let mut style = Style::default().add_modifier(Modifier::BOLD);
+                    if *category == review::Category::Overdue {
+                        style = style.fg(Color::Red);
+                    }
+                    rwidgets::ListItem::new(Span::styled(category.title(), style))
+                }
+                ReviewRow::Item(index) => {
+                    heights.push(2);
+                    let item = &self.items[*index];
+                    let style =
+                        if item.category != review::Category::Completed && !item.node.is_open() {
+                            dim.add_modifier(Modifier::CROSSED_OUT)
+                        } else {
+                            Style::default()
+                        };
+                    let mut title = vec![Span::styled(item.node.title.clone(), style)];
+                    if let Some(due) = item.node.due {
+                        let due = util::format_date_time(due);
+                        title.push(Span::styled(format!("  due {}", due), dim));
+                    }
+                    if item.category == review::Category::Stale {
+                        let reviewed = match item.reviewed {
+                            Some(reviewed) => {
+                                format!("  reviewed {}", util::format_date_time(reviewed))
+                            }
+                            None => "  never reviewed".to_string(),
+                        };
+                        title.push(Span::styled(reviewed, dim));
+                    }
+
+                    let path: Vec<&str> =
+                        item.path.iter().map(|node| node.title.as_str()).collect();
+                    let path = Span::styled(format!("  {}", path.join(" / ")), dim);
+                    rwidgets::ListItem::new(vec![Spans::from(title), Spans::from(path)])
+                }
+            })
+            .collect();
+
+        let selected = self.item_list.selected();
+        let selected_row = self.rows.iter().position(|row| match row {
+            ReviewRow::Item(index) => Some(*index) == selected,
+            ReviewRow::Header(_) => false,
+        });
+
+        let title = if self.items.is_empty() {
+            "Review: nothing needs looking at"
+        } else {
+            "Review"
+        };
+        let list = rwidgets::List::new(items)
+            .block(
+                rwidgets::Block::default()
+                    .title(title)
+                    .borders(rwidgets::Borders::all())
+                    .border_style(theme.border_style()),
+            )
+            .highlight_style(theme.highlight_style())
+            .highlight_symbol(">>");
+        self.item_list.render(f, list, rect, heights, selected_row);
+    }
+}
@@ -1,46 +1,168 @@
-use crossterm::event::KeyCode;
-use crossterm::event::KeyEvent;
+use std::ops::Range;
 
-pub fn handle_input_single_line(target: &mut String, evt: KeyEvent) {
-    match evt.code {
-        KeyCode::Char(c) => {
-            target.push(c);
+use anyhow::anyhow;
+use chrono::DateTime;
+use chrono::Duration;
+use chrono::Local;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono::Utc;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Soft-wraps `target` into lines no wider than `width` columns,
+/// returning the byte range of each visual line.
+/// Lines break after whitespace where possible and mid-word otherwise.
+/// Whitespace is allowed to hang off the end of a line
+/// so that wrapped lines don't start with a space.
+pub fn wrap(target: &str, width: u16) -> Vec<Range<usize>> {
+    let width = width.max(1) as usize;
+
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+    for logical_line in target.split('\n') {
+        let logical_end = line_start + logical_line.len();
+
+        let mut start = line_start;
+        let mut line_width = 0;
+        // The byte offset just after the last whitespace on the current visual line.
+        let mut last_break = None;
+        for (offset, grapheme) in logical_line.grapheme_indices(true) {
+            let offset = line_start + offset;
+            let grapheme_width = grapheme.width();
+            let is_whitespace = grapheme.chars().all(char::is_whitespace);
+
+            if line_width + grapheme_width > width && !is_whitespace && offset > start {
+                let end = last_break.unwrap_or(offset);
+                lines.push(start..end);
+                line_width = target[end..offset].width();
+                start = end;
+                last_break = None;
+            }
+
+            line_width += grapheme_width;
+            if is_whitespace {
+                last_break = Some(offset + grapheme.len());
+            }
         }
-        KeyCode::Backspace => {
-            target.pop();
+        lines.push(start..logical_end);
+
+        // Skip over the '\n'.
+        line_start = logical_end + 1;
+    }
+    lines
+}
+
+/// Finds where the cursor at byte offset `cursor` lands once `target` is wrapped to `width`.
+/// The `x` coordinate is offset by one to account for the left border around text fields,
+/// so the cursor at the end of a line falls on the point _after_ its last character.
+pub fn cursor_offset(target: &str, cursor: usize, width: u16) -> (u16, u16) {
+    let lines = wrap(target, width);
+    let (y, line) = cursor_line(&lines, cursor);
+    let x = 1 + target[line.start..cursor].width() as u16;
+    (x, y as u16)
+}
+
+/// Finds the visual line that contains `cursor`.
+/// A cursor sitting on the boundary between two wrapped lines belongs to the later one.
+pub fn cursor_line(lines: &[Range<usize>], cursor: usize) -> (usize, Range<usize>) {
+    for (i, line) in lines.iter().enumerate() {
+        let is_last = i == lines.len() - 1;
+        let next_starts_here = !is_last && lines[i + 1].start == cursor;
+        if line.start <= cursor && cursor <= line.end && !next_starts_here {
+            return (i, line.clone());
         }
-        _ => {}
     }
+    let last = lines.len() - 1;
+    (last, lines[last].clone())
 }
 
-pub fn handle_input_multi_line(target: &mut String, evt: KeyEvent) {
-    match evt.code {
-        KeyCode::Char(c) => {
-            target.push(c);
+/// Works out which item a `List` starts drawing from, given the one it started from last time.
+/// `ListState` keeps this private, so this follows the same rules `List` does
+/// for keeping the selected item on screen.
+pub fn list_offset(
+    offset: usize,
+    selected: Option<usize>,
+    heights: &[usize],
+    max_height: usize,
+) -> usize {
+    if heights.is_empty() {
+        return 0;
+    }
+    // Clearing the selection resets the offset.
+    let Some(selected) = selected else { return 0 };
+    let selected = selected.min(heights.len() - 1);
+
+    let mut start = offset.min(heights.len() - 1);
+    let mut end = start;
+    let mut height = 0;
+    for item_height in heights.iter().skip(start) {
+        if height + item_height > max_height {
+            break;
         }
-        KeyCode::Enter => {
-            target.push('\n');
+        height += item_height;
+        end += 1;
+    }
+
+    while selected >= end {
+        height += heights[end];
+        end += 1;
+        while height > max_height {
+            height -= heights[start];
+            start += 1;
         }
-        KeyCode::Backspace => {
-            target.pop();
+    }
+    while selected < start {
+        start -= 1;
+        height += heights[start];
+        while height > max_height {
+            end -= 1;
+            height -= heights[end];
         }
-        _ => {}
     }
+    start
 }
 
-pub fn cursor_offset(target: &str) -> (u16, u16) {
-    // TODO: fix some things with this:
-    // - handle wrapping
-    //   - make the `x` coordinate fit horizontally along the rune-length of the last section of a wrapped line
-    //   - make the `y` coordinate account for the vertical length of wrapped lines
-    let lines: Vec<&str> = target.split('\n').collect();
-    let mut x = 1; // offset by one so the cursor falls on the point _after_ the last character
-    if let Some(last_line) = lines.last() {
-	let chars: Vec<char> = last_line.chars().collect();
-	x += chars.len() as u16;
-    }
-    let y = lines.len() as u16 - 1;
-    (x, y)
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// Formats a date in local time, leaving off the time when it's midnight.
+pub fn format_date_time(date_time: DateTime<Utc>) -> String {
+    let local = date_time.with_timezone(&Local);
+    if local.time() == NaiveTime::MIN {
+        local.format(DATE_FORMAT).to_string()
+    } else {
+        local.format(DATE_TIME_FORMAT).to_string()
+    }
+}
+
+/// Parses a date typed into the UI, in local time.
+/// Accepts `YYYY-MM-DD`, `YYYY-MM-DD HH:MM`, `today` and `tomorrow`.
+/// An empty string clears the date.
+pub fn parse_date_time(text: &str) -> anyhow::Result<Option<DateTime<Utc>>> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+
+    let today = Local::now().date_naive();
+    let naive = match text {
+        "today" => today.and_time(NaiveTime::MIN),
+        "tomorrow" => (today + Duration::days(1)).and_time(NaiveTime::MIN),
+        _ => NaiveDateTime::parse_from_str(text, DATE_TIME_FORMAT)
+            .or_else(|_| {
+                NaiveDate::parse_from_str(text, DATE_FORMAT)
+                    .map(|date| date.and_time(NaiveTime::MIN))
+            })
+            .map_err(|_| anyhow!("Expected a date like 2023-04-01 or 2023-04-01 09:30"))?,
+    };
+    let local = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .ok_or_else(|| anyhow!("{} doesn't exist in the local timezone", text))?;
+    Ok(Some(local.with_timezone(&Utc)))
 }
 
 #[cfg(test)]
@@ -50,27 +172,110 @@ mod tests {
 
     #[test]
     fn test_cursor_output__empty() {
-	let offset = cursor_offset("");
-	assert_eq!(offset, (1, 0));
+        let offset = cursor_offset("", 0, 80);
+        assert_eq!(offset, (1, 0));
     }
 
     #[test]
     fn test_cursor_output__single_line() {
-	let offset = cursor_offset("here's some characters");
-	assert_eq!(offset, (23, 0));
+        let target = "here's some characters";
+        let offset = cursor_offset(target, target.len(), 80);
+        assert_eq!(offset, (23, 0));
     }
 
     #[test]
     fn test_cursor_output__single_line_rune() {
-	// note the special `’` is multiple bytes long
-	let offset = cursor_offset("here’s some characters");
-	assert_eq!(offset, (23, 0));
+        // note the special `’` is multiple bytes long
+        let target = "here’s some characters";
+        let offset = cursor_offset(target, target.len(), 80);
+        assert_eq!(offset, (23, 0));
     }
 
     #[test]
     fn test_cursor_output__multi_line() {
-	// note the special `’` is multiple bytes long
-	let offset = cursor_offset("here's some characters\nacross multiple lines");
-	assert_eq!(offset, (22, 1));
+        // note the special `’` is multiple bytes long
+        let target = "here's some characters\nacross multiple lines";
+        let offset = cursor_offset(target, target.len(), 80);
+        assert_eq!(offset, (22, 1));
+    }
+
+    #[test]
+    fn test_cursor_output__wrapped() {
+        let target = "here's some characters";
+        let offset = cursor_offset(target, target.len(), 12);
+        assert_eq!(offset, (11, 1));
+    }
+
+    #[test]
+    fn test_cursor_output__middle() {
+        let target = "here's some characters";
+        let offset = cursor_offset(target, 4, 80);
+        assert_eq!(offset, (5, 0));
+    }
+
+    #[test]
+    fn test_wrap__breaks_after_whitespace() {
+        let target = "here's some characters";
+        let lines: Vec<&str> = wrap(target, 12)
+            .into_iter()
+            .map(|range| &target[range])
+            .collect();
+        assert_eq!(lines, vec!["here's some ", "characters"]);
+    }
+
+    #[test]
+    fn test_wrap__breaks_long_words() {
+        let target = "abcdefghij";
+        let lines: Vec<&str> = wrap(target, 4)
+            .into_iter()
+            .map(|range| &target[range])
+            .collect();
+        assert_eq!(lines, vec!["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn test_wrap__keeps_empty_lines() {
+        let target = "a\n\nb";
+        let lines: Vec<&str> = wrap(target, 4)
+            .into_iter()
+            .map(|range| &target[range])
+            .collect();
+        assert_eq!(lines, vec!["a", "", "b"]);
+    }
+
+    #[test]
+    fn test_list_offset__scrolls_down_to_selection() {
+        assert_eq!(list_offset(0, Some(2), &[1; 5], 5), 0);
+        assert_eq!(list_offset(0, Some(6), &[1; 10], 5), 2);
+    }
+
+    #[test]
+    fn test_list_offset__keeps_offset_while_selection_visible() {
+        assert_eq!(list_offset(2, Some(4), &[1; 10], 5), 2);
+        assert_eq!(list_offset(4, Some(1), &[1; 10], 5), 1);
+    }
+
+    #[test]
+    fn test_list_offset__tall_items() {
+        assert_eq!(list_offset(0, Some(3), &[2; 5], 6), 1);
+    }
+
+    #[test]
+    fn test_parse_date_time__round_trips() {
+        for text in ["2023-04-01", "2023-04-01 09:30"] {
+            let date_time = parse_date_time(text).unwrap().unwrap();
+            assert_eq!(format_date_time(date_time), text);
+        }
+    }
+
+    #[test]
+    fn test_parse_date_time__empty_clears() {
+        assert!(parse_date_time("  ").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_parse_date_time__invalid() {
+        assert!(parse_date_time("next week").is_err());
+        assert!(parse_date_time("2023-13-01").is_err());
     }
 }
@@ -1,21 +1,41 @@
-use std::rc::Rc;
+use std::ops::Range;
+use std::time::Instant;
 
+use chrono::DateTime;
+use chrono::Utc;
 use crossterm::event::KeyCode;
 use crossterm::event::KeyEvent;
+use crossterm::event::KeyModifiers;
+use crossterm::event::MouseButton;
+use crossterm::event::MouseEvent;
+use crossterm::event::MouseEventKind;
+use ratatui::backend::Backend;
 use ratatui::buffer::Buffer;
 use ratatui::layout;
+use ratatui::style::Color;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::text::Span;
+use ratatui::text::Spans;
 use ratatui::widgets;
 use ratatui::widgets::StatefulWidget;
 use ratatui::widgets::Widget;
+use ratatui::Frame;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
 
 use crate::db;
 use crate::ui::util;
 
-pub struct NodeEditor {}
+#[derive(Default)]
+pub struct NodeEditor {
+    border_style: Style,
+}
 
-impl Default for NodeEditor {
-    fn default() -> Self {
-        Self {}
+impl NodeEditor {
+    pub fn border_style(mut self, border_style: Style) -> Self {
+        self.border_style = border_style;
+        self
     }
 }
 
@@ -23,96 +43,293 @@ impl StatefulWidget for NodeEditor {
     type State = NodeEditorState;
 
     fn render(self, area: layout::Rect, buf: &mut Buffer, state: &mut NodeEditorState) {
-	let parts = state.segment_area(area);
-        let (title, description) = match &state.node {
-            None => ("N/A".to_string(), "No node selected".to_string()),
-            Some(node) => (node.title.clone(), node.description.clone()),
+        let parts = state.segment_area(area);
+        let block = widgets::Block::default()
+            .borders(widgets::Borders::all())
+            .border_style(self.border_style);
+
+        let Some(node) = &state.node else {
+            widgets::Paragraph::new("N/A")
+                .block(block.clone())
+                .render(parts.title, buf);
+            widgets::Paragraph::new("No node selected")
+                .block(block)
+                .render(parts.description, buf);
+            return;
         };
 
-        // TODO: handle rendering strings which are longer than the width of `area`
-        let top = widgets::Paragraph::new(title)
-            .block(widgets::Block::default().borders(widgets::Borders::all()));
-        top.render(parts[0], buf);
+        let status = if node.trashed {
+            Some("Trashed".to_string())
+        } else {
+            node.completed
+                .map(|completed| format!("Completed {}", util::format_date_time(completed)))
+        };
+        let mut title = Vec::new();
+        if let Some(status) = status {
+            title.push(Span::raw(status));
+        }
+        if state.is_dirty() {
+            if !title.is_empty() {
+                title.push(Span::raw(" "));
+            }
+            title.push(Span::styled("[unsaved]", Style::default().fg(Color::Yellow)));
+        }
+        let title_block = block.clone().title(Spans::from(title));
+        let overdue = node.is_open() && node.due.is_some_and(|due| due < Utc::now());
 
-        let bottom = widgets::Paragraph::new(description)
-            .block(widgets::Block::default().borders(widgets::Borders::all()));
-        bottom.render(parts[1], buf);
+        TextArea::default()
+            .block(title_block)
+            .render(parts.title, buf, &mut state.title);
+        TextArea::default()
+            .block(date_block(block.clone(), "Scheduled", &state.scheduled, false))
+            .render(parts.scheduled, buf, &mut state.scheduled);
+        TextArea::default()
+            .block(date_block(block.clone(), "Due", &state.due, overdue))
+            .render(parts.due, buf, &mut state.due);
+        TextArea::default()
+            .block(block)
+            .render(parts.description, buf, &mut state.description);
     }
 }
 
+/// Titles a date field, calling out dates which don't parse.
+fn date_block<'a>(
+    block: widgets::Block<'a>,
+    name: &'a str,
+    state: &TextAreaState,
+    highlight: bool,
+) -> widgets::Block<'a> {
+    let red = Style::default().fg(Color::Red);
+    if util::parse_date_time(state.text()).is_err() {
+        block.title(Span::styled(format!("{} (invalid)", name), red))
+    } else if highlight {
+        block.title(Span::styled(name, red))
+    } else {
+        block.title(name)
+    }
+}
+
+struct NodeEditorAreas {
+    title: layout::Rect,
+    scheduled: layout::Rect,
+    due: layout::Rect,
+    description: layout::Rect,
+}
+
 pub struct NodeEditorState {
     node: Option<db::Node>,
+    title: TextAreaState,
+    scheduled: TextAreaState,
+    due: TextAreaState,
+    description: TextAreaState,
     mode: NodeEditorMode,
+    /// The node as it was loaded or last saved,
+    /// so that saving can tell whether someone else changed it in the meantime.
+    original: Option<db::Node>,
+    /// When the node was last changed, if it's changed since it was last saved.
+    last_edit: Option<Instant>,
 }
 
 impl NodeEditorState {
     pub fn new(node: Option<db::Node>) -> Self {
-        Self {
-            node,
+        let mut state = Self {
+            node: None,
+            title: TextAreaState::single_line(""),
+            scheduled: TextAreaState::single_line(""),
+            due: TextAreaState::single_line(""),
+            description: TextAreaState::multi_line(""),
             mode: NodeEditorMode::Title,
-        }
+            original: None,
+            last_edit: None,
+        };
+        state.select(node);
+        state
     }
 
     pub fn select(&mut self, new_node: Option<db::Node>) {
+        self.show(new_node.clone());
+        self.original = new_node;
+        self.last_edit = None;
+    }
+
+    /// Shows `ours` as unsaved edits on top of `theirs`,
+    /// for when someone else saved the node first.
+    pub fn rebase(&mut self, theirs: db::Node, ours: db::Node) {
+        let mode = self.mode;
+        self.show(Some(ours));
+        self.mode = mode;
+        self.original = Some(theirs);
+        self.last_edit = Some(Instant::now());
+    }
+
+    fn show(&mut self, new_node: Option<db::Node>) {
+        let (title, description) = match &new_node {
+            None => ("", ""),
+            Some(node) => (node.title.as_str(), node.description.as_str()),
+        };
+        let date = |date: Option<DateTime<Utc>>| date.map(util::format_date_time).unwrap_or_default();
+        let scheduled = date(new_node.as_ref().and_then(|node| node.scheduled));
+        let due = date(new_node.as_ref().and_then(|node| node.due));
+
+        self.title.set_text(title);
+        self.scheduled.set_text(&scheduled);
+        self.due.set_text(&due);
+        self.description.set_text(description);
         self.node = new_node;
         self.mode = NodeEditorMode::Title;
     }
 
     pub fn node(&self) -> Option<&db::Node> {
-	self.node.as_ref()
+        self.node.as_ref()
     }
 
-    pub fn handle_input(&mut self, evt: KeyEvent) {
-        let Some(node) = &mut self.node else { return };
+    pub fn is_dirty(&self) -> bool {
+        self.last_edit.is_some()
+    }
 
-        let (target, allow_newline) = match self.mode {
-            NodeEditorMode::Title => (&mut node.title, false),
-            NodeEditorMode::Description => (&mut node.description, true),
-        };
-        match evt.code {
-            KeyCode::Tab => self.mode = self.mode.next(),
-            KeyCode::Char(c) => {
-                target.push(c);
-            }
-            KeyCode::Enter if allow_newline => {
-                target.push('\n');
-            }
-            KeyCode::Backspace => {
-                target.pop();
-            }
-            _ => {}
+    pub fn last_edit(&self) -> Option<Instant> {
+        self.last_edit
+    }
+
+    pub fn original(&self) -> Option<&db::Node> {
+        self.original.as_ref()
+    }
+
+    /// Records that the node, as it stands, has been written to the database.
+    pub fn mark_saved(&mut self) {
+        self.original = self.node.clone();
+        self.last_edit = None;
+    }
+
+    pub fn focus_title(&mut self) {
+        self.mode = NodeEditorMode::Title;
+    }
+
+    pub fn focus_scheduled(&mut self) {
+        self.mode = NodeEditorMode::Scheduled;
+    }
+
+    pub fn focus_due(&mut self) {
+        self.mode = NodeEditorMode::Due;
+    }
+
+    pub fn focus_description(&mut self) {
+        self.mode = NodeEditorMode::Description;
+    }
+
+    pub fn focus(&mut self, mode: NodeEditorMode) {
+        self.mode = mode;
+    }
+
+    /// Finds the field drawn at `(column, row)` when the editor is rendered into `area`.
+    pub fn field_at(&self, area: layout::Rect, column: u16, row: u16) -> Option<NodeEditorMode> {
+        self.node.as_ref()?;
+        let parts = self.segment_area(area);
+        let point = layout::Rect::new(column, row, 1, 1);
+        [
+            (parts.title, NodeEditorMode::Title),
+            (parts.scheduled, NodeEditorMode::Scheduled),
+            (parts.due, NodeEditorMode::Due),
+            (parts.description, NodeEditorMode::Description),
+        ]
+        .into_iter()
+        .find(|(area, _)| area.intersects(point))
+        .map(|(_, mode)| mode)
+    }
+
+    pub fn focus_next(&mut self) {
+        self.mode = self.mode.next();
+    }
+
+    pub fn focus_prev(&mut self) {
+        self.mode = self.mode.prev();
+    }
+
+    /// Handles a key press, returning whether the node was changed.
+    pub fn handle_input(&mut self, evt: KeyEvent) -> bool {
+        if self.node.is_none() {
+            return false;
         }
+
+        let changed = self.focused_mut().handle_input(evt);
+        if changed {
+            self.sync_node();
+        }
+        changed
+    }
+
+    pub fn paste(&mut self, text: &str) {
+        if self.node.is_none() {
+            return;
+        }
+        self.focused_mut().paste(text);
+        self.sync_node();
     }
 
     pub fn cursor_offset(&self, area: layout::Rect) -> Option<(u16, u16)> {
-	let Some(node) = &self.node else { return None };
-	let parts = self.segment_area(area);
-	let ((x, y), part) = match self.mode {
-	    NodeEditorMode::Title => (util::cursor_offset(&node.title), parts[0]),
-	    NodeEditorMode::Description => (util::cursor_offset(&node.description), parts[1]),
-	};
-	Some((
-	    // NOTE: NodeEditor adds a margin of 1 to an `area`
-	    // because it uses Borders::all().
-	    x + part.x,
-	    y + part.y + 1,
-	))
-    }
-
-    fn segment_area(&self, area: layout::Rect) -> Rc<[layout::Rect]> {
-        layout::Layout::default()
+        self.node.as_ref()?;
+        let parts = self.segment_area(area);
+        Some(match self.mode {
+            NodeEditorMode::Title => self.title.cursor_offset(parts.title),
+            NodeEditorMode::Scheduled => self.scheduled.cursor_offset(parts.scheduled),
+            NodeEditorMode::Due => self.due.cursor_offset(parts.due),
+            NodeEditorMode::Description => self.description.cursor_offset(parts.description),
+        })
+    }
+
+    fn focused_mut(&mut self) -> &mut TextAreaState {
+        match self.mode {
+            NodeEditorMode::Title => &mut self.title,
+            NodeEditorMode::Scheduled => &mut self.scheduled,
+            NodeEditorMode::Due => &mut self.due,
+            NodeEditorMode::Description => &mut self.description,
+        }
+    }
+
+    fn sync_node(&mut self) {
+        let Some(node) = &mut self.node else { return };
+        self.last_edit = Some(Instant::now());
+        node.title = self.title.text().to_string();
+        node.description = self.description.text().to_string();
+        // Dates which are still being typed keep their old value until they parse.
+        if let Ok(scheduled) = util::parse_date_time(self.scheduled.text()) {
+            node.scheduled = scheduled;
+        }
+        if let Ok(due) = util::parse_date_time(self.due.text()) {
+            node.due = due;
+        }
+    }
+
+    fn segment_area(&self, area: layout::Rect) -> NodeEditorAreas {
+        let rows = layout::Layout::default()
             .direction(layout::Direction::Vertical)
             .constraints([
+                layout::Constraint::Length(3),
+                layout::Constraint::Length(3),
                 layout::Constraint::Min(3),
-                layout::Constraint::Percentage(100),
             ])
-            .split(area)
+            .split(area);
+        let dates = layout::Layout::default()
+            .direction(layout::Direction::Horizontal)
+            .constraints([
+                layout::Constraint::Percentage(50),
+                layout::Constraint::Percentage(50),
+            ])
+            .split(rows[1]);
+        NodeEditorAreas {
+            title: rows[0],
+            scheduled: dates[0],
+            due: dates[1],
+            description: rows[2],
+        }
     }
 }
 
-#[derive(Clone, Copy)]
-enum NodeEditorMode {
+#[derive(Clone, Copy, PartialEq)]
+pub enum NodeEditorMode {
     Title,
+    Scheduled,
+    Due,
     Description,
 }
 
@@ -120,8 +337,624 @@ impl NodeEditorMode {
     fn next(self) -> Self {
         use NodeEditorMode::*;
         match self {
-            Title => Description,
+            Title => Scheduled,
+            Scheduled => Due,
+            Due => Description,
             Description => Title,
         }
     }
+
+    fn prev(self) -> Self {
+        use NodeEditorMode::*;
+        match self {
+            Title => Description,
+            Scheduled => Title,
+            Due => Scheduled,
+            Description => Due,
+        }
+    }
+}
+
+/// Where a bordered `List` was last drawn,
+/// so that mouse events can be mapped back onto its items.
+#[derive(Default)]
+pub struct ListHitbox {
+    area: layout::Rect,
+    offset: usize,
+    heights: Vec<usize>,
+}
+
+impl ListHitbox {
+    /// Records a list about to be drawn into `area` with items `heights` rows tall.
+    pub fn update(&mut self, area: layout::Rect, state: &widgets::ListState, heights: Vec<usize>) {
+        let max_height = area.height.saturating_sub(2) as usize;
+        self.offset = util::list_offset(self.offset, state.selected(), &heights, max_height);
+        self.area = area;
+        self.heights = heights;
+    }
+
+    /// The items which fit on screen when the list is drawn with `selected` selected,
+    /// going by where it was last drawn.
+    pub fn visible(&self, selected: Option<usize>) -> Range<usize> {
+        let max_height = self.area.height.saturating_sub(2) as usize;
+        let start = util::list_offset(self.offset, selected, &self.heights, max_height);
+        let mut end = start;
+        let mut height = 0;
+        for item_height in self.heights.iter().skip(start) {
+            if height + item_height > max_height {
+                break;
+            }
+            height += item_height;
+            end += 1;
+        }
+        start..end
+    }
+
+    pub fn contains(&self, column: u16, row: u16) -> bool {
+        self.area.intersects(layout::Rect::new(column, row, 1, 1))
+    }
+
+    /// The item drawn at `(column, row)`, if there is one.
+    pub fn index_at(&self, column: u16, row: u16) -> Option<usize> {
+        let inner = self.area.inner(&layout::Margin {
+            vertical: 1,
+            horizontal: 1,
+        });
+        if !inner.intersects(layout::Rect::new(column, row, 1, 1)) {
+            return None;
+        }
+
+        let mut top = inner.y as usize;
+        for (i, height) in self.heights.iter().enumerate().skip(self.offset) {
+            if (row as usize) < top + height {
+                return Some(i);
+            }
+            top += height;
+        }
+        None
+    }
+}
+
+/// The selection in a list that's moved through with the arrow keys, the wheel and clicks,
+/// along with what's needed to draw it and map clicks back onto it.
+/// The selection is an index into the list's items, which can differ from the rows drawn
+/// when the list has rows that can't be selected, like headings.
+#[derive(Default)]
+pub struct SelectableList {
+    selected: Option<usize>,
+    state: widgets::ListState,
+    hitbox: ListHitbox,
+}
+
+impl SelectableList {
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, selected: Option<usize>) {
+        self.selected = selected;
+    }
+
+    /// Selects the first of `len` items, if there are any.
+    pub fn select_first(&mut self, len: usize) {
+        self.selected = if len == 0 { None } else { Some(0) };
+    }
+
+    pub fn go_up(&mut self) {
+        let Some(selected) = self.selected else { return };
+        if selected > 0 {
+            self.selected = Some(selected - 1);
+        }
+    }
+
+    pub fn go_down(&mut self, len: usize) {
+        let Some(selected) = self.selected else { return };
+        if selected + 1 < len {
+            self.selected = Some(selected + 1);
+        }
+    }
+
+    /// Scrolls with the wheel, or selects the clicked item.
+    /// `item_at` maps a row of the list onto the item it shows, if it shows one.
+    /// Returns whether the selected item was double clicked.
+    pub fn handle_mouse(
+        &mut self,
+        evt: MouseEvent,
+        double_click: bool,
+        len: usize,
+        item_at: impl Fn(usize) -> Option<usize>,
+    ) -> bool {
+        if !self.hitbox.contains(evt.column, evt.row) {
+            return false;
+        }
+        match evt.kind {
+            MouseEventKind::ScrollUp => self.go_up(),
+            MouseEventKind::ScrollDown => self.go_down(len),
+            MouseEventKind::Down(MouseButton::Left) => {
+                let row = self.hitbox.index_at(evt.column, evt.row);
+                let Some(index) = row.and_then(item_at) else { return false };
+                if double_click && self.selected == Some(index) {
+                    return true;
+                }
+                self.selected = Some(index);
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Draws `list` into `area`, with `selected_row` highlighted.
+    /// Rows are `heights` lines tall.
+    pub fn render(
+        &mut self,
+        f: &mut Frame<impl Backend>,
+        list: widgets::List,
+        area: layout::Rect,
+        heights: Vec<usize>,
+        selected_row: Option<usize>,
+    ) {
+        self.state.select(selected_row);
+        self.hitbox.update(area, &self.state, heights);
+        f.render_stateful_widget(list, area, &mut self.state);
+    }
+}
+
+/// An editable block of text which soft-wraps to the width it's rendered at.
+#[derive(Default)]
+pub struct TextArea<'a> {
+    block: Option<widgets::Block<'a>>,
+}
+
+impl<'a> TextArea<'a> {
+    pub fn block(mut self, block: widgets::Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl StatefulWidget for TextArea<'_> {
+    type State = TextAreaState;
+
+    fn render(self, area: layout::Rect, buf: &mut Buffer, state: &mut TextAreaState) {
+        let inner = match self.block {
+            None => area,
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+        };
+        if inner.width == 0 || inner.height == 0 {
+            return;
+        }
+        state.width = inner.width;
+
+        let lines = util::wrap(&state.text, inner.width);
+        let (cursor_y, _) = util::cursor_line(&lines, state.cursor);
+        let height = inner.height as usize;
+        if cursor_y < state.scroll {
+            state.scroll = cursor_y;
+        } else if cursor_y >= state.scroll + height {
+            state.scroll = cursor_y + 1 - height;
+        }
+
+        let selection = state.selection();
+        let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+        for (row, line) in lines.iter().skip(state.scroll).take(height).enumerate() {
+            let mut x = inner.x;
+            for (offset, grapheme) in state.text[line.clone()].grapheme_indices(true) {
+                let offset = line.start + offset;
+                let width = grapheme.width() as u16;
+                // Whitespace can hang past the end of a wrapped line.
+                if x + width > inner.right() {
+                    break;
+                }
+                let style = match &selection {
+                    Some(selection) if selection.contains(&offset) => selected_style,
+                    _ => Style::default(),
+                };
+                buf.set_string(x, inner.y + row as u16, grapheme, style);
+                x += width;
+            }
+        }
+    }
+}
+
+pub struct TextAreaState {
+    text: String,
+    /// Byte offset of the cursor, always on a grapheme boundary.
+    cursor: usize,
+    /// Where the selection started, if there is one. The selection runs from here to `cursor`.
+    anchor: Option<usize>,
+    multi_line: bool,
+    // These are remembered from the last render,
+    // so that we can move between wrapped lines and place the cursor.
+    width: u16,
+    scroll: usize,
+}
+
+impl TextAreaState {
+    pub fn single_line(text: &str) -> Self {
+        Self::new(text, false)
+    }
+
+    pub fn multi_line(text: &str) -> Self {
+        Self::new(text, true)
+    }
+
+    fn new(text: &str, multi_line: bool) -> Self {
+        let mut state = Self {
+            text: String::new(),
+            cursor: 0,
+            anchor: None,
+            multi_line,
+            width: 0,
+            scroll: 0,
+        };
+        state.set_text(text);
+        state
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the contents of the text area, putting the cursor at the end.
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+        self.anchor = None;
+        self.scroll = 0;
+    }
+
+    /// Handles a key press, returning whether the text was changed.
+    pub fn handle_input(&mut self, evt: KeyEvent) -> bool {
+        let ctrl = evt.modifiers.contains(KeyModifiers::CONTROL);
+        let shift = evt.modifiers.contains(KeyModifiers::SHIFT);
+        match evt.code {
+            KeyCode::Char('w') if ctrl => {
+                self.delete_selection() || self.delete(self.word_left(), self.cursor)
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.delete_selection() || self.delete(self.line_start(), self.cursor)
+            }
+            KeyCode::Char(_) if ctrl => false,
+            KeyCode::Char(c) => {
+                self.insert(c.encode_utf8(&mut [0; 4]));
+                true
+            }
+            KeyCode::Enter if self.multi_line => {
+                self.insert("\n");
+                true
+            }
+            KeyCode::Backspace => {
+                self.delete_selection() || self.delete(self.prev_grapheme(), self.cursor)
+            }
+            KeyCode::Delete => {
+                self.delete_selection() || self.delete(self.cursor, self.next_grapheme())
+            }
+            KeyCode::Left if ctrl => self.move_to(self.word_left(), shift),
+            KeyCode::Left => self.move_to(self.prev_grapheme(), shift),
+            KeyCode::Right if ctrl => self.move_to(self.word_right(), shift),
+            KeyCode::Right => self.move_to(self.next_grapheme(), shift),
+            KeyCode::Home => self.move_to(self.line_start(), shift),
+            KeyCode::End => self.move_to(self.line_end(), shift),
+            KeyCode::Up if self.multi_line => self.move_to(self.vertical(-1), shift),
+            KeyCode::Down if self.multi_line => self.move_to(self.vertical(1), shift),
+            _ => false,
+        }
+    }
+
+    /// Inserts pasted text at the cursor.
+    /// Single-line text areas get newlines turned into spaces.
+    pub fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        if self.multi_line {
+            self.insert(&text);
+        } else {
+            self.insert(&text.replace('\n', " "));
+        }
+    }
+
+    /// Where to draw the cursor, given the same `area` the text area was rendered into.
+    /// This assumes the text area was rendered with a border.
+    pub fn cursor_offset(&self, area: layout::Rect) -> (u16, u16) {
+        let width = if self.width == 0 {
+            area.width.saturating_sub(2)
+        } else {
+            self.width
+        };
+        let (x, y) = util::cursor_offset(&self.text, self.cursor, width);
+        let y = (y as usize).saturating_sub(self.scroll) as u16;
+        (
+            (area.x + x).min(area.right().saturating_sub(2)),
+            area.y + 1 + y,
+        )
+    }
+
+    fn selection(&self) -> Option<Range<usize>> {
+        let anchor = self.anchor?;
+        if anchor == self.cursor {
+            return None;
+        }
+        Some(anchor.min(self.cursor)..anchor.max(self.cursor))
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /// Deletes the selected text, returning whether there was any.
+    fn delete_selection(&mut self) -> bool {
+        let Some(selection) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+        self.delete(selection.start, selection.end);
+        true
+    }
+
+    /// Deletes the text between `start` and `end`, returning whether there was any.
+    fn delete(&mut self, start: usize, end: usize) -> bool {
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+        self.anchor = None;
+        start < end
+    }
+
+    /// Moves the cursor, extending the selection if `extend` is set.
+    /// Always returns false, since moving the cursor never changes the text.
+    fn move_to(&mut self, position: usize, extend: bool) -> bool {
+        if extend {
+            self.anchor.get_or_insert(self.cursor);
+        } else {
+            self.anchor = None;
+        }
+        self.cursor = position;
+        false
+    }
+
+    fn prev_grapheme(&self) -> usize {
+        self.text[..self.cursor]
+            .grapheme_indices(true)
+            .next_back()
+            .map(|(offset, _)| offset)
+            .unwrap_or(0)
+    }
+
+    fn next_grapheme(&self) -> usize {
+        self.text[self.cursor..]
+            .graphemes(true)
+            .next()
+            .map(|grapheme| self.cursor + grapheme.len())
+            .unwrap_or(self.text.len())
+    }
+
+    fn word_left(&self) -> usize {
+        let mut position = self.cursor;
+        let mut seen_word = false;
+        for (offset, grapheme) in self.text[..self.cursor].grapheme_indices(true).rev() {
+            let is_whitespace = grapheme.chars().all(char::is_whitespace);
+            if is_whitespace && seen_word {
+                break;
+            }
+            seen_word |= !is_whitespace;
+            position = offset;
+        }
+        position
+    }
+
+    fn word_right(&self) -> usize {
+        let mut position = self.cursor;
+        let mut seen_word = false;
+        for grapheme in self.text[self.cursor..].graphemes(true) {
+            let is_whitespace = grapheme.chars().all(char::is_whitespace);
+            if is_whitespace && seen_word {
+                break;
+            }
+            seen_word |= !is_whitespace;
+            position += grapheme.len();
+        }
+        position
+    }
+
+    fn line_start(&self) -> usize {
+        self.text[..self.cursor]
+            .rfind('\n')
+            .map(|offset| offset + 1)
+            .unwrap_or(0)
+    }
+
+    fn line_end(&self) -> usize {
+        self.text[self.cursor..]
+            .find('\n')
+            .map(|offset| self.cursor + offset)
+            .unwrap_or(self.text.len())
+    }
+
+    /// Finds the position `lines` visual lines above (negative) or below (positive) the cursor,
+    /// keeping to the same column where possible.
+    fn vertical(&self, lines: isize) -> usize {
+        let width = if self.width == 0 {
+            u16::MAX
+        } else {
+            self.width
+        };
+        let wrapped = util::wrap(&self.text, width);
+        let (y, line) = util::cursor_line(&wrapped, self.cursor);
+        let column = self.text[line.start..self.cursor].width();
+
+        let target = y as isize + lines;
+        if target < 0 {
+            return 0;
+        }
+        let target = target as usize;
+        let Some(target_line) = wrapped.get(target) else {
+            return self.text.len();
+        };
+
+        // The end of a soft-wrapped line is the start of the next one,
+        // so we have to stop one grapheme short of it.
+        let is_wrapped = wrapped
+            .get(target + 1)
+            .map(|next| next.start == target_line.end)
+            .unwrap_or(false);
+
+        let mut position = target_line.start;
+        let mut width = 0;
+        for grapheme in self.text[target_line.clone()].graphemes(true) {
+            width += grapheme.width();
+            if width > column {
+                return position;
+            }
+            position += grapheme.len();
+        }
+        if is_wrapped {
+            self.text[..position]
+                .grapheme_indices(true)
+                .next_back()
+                .map(|(offset, _)| offset)
+                .unwrap_or(position)
+        } else {
+            position
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(non_snake_case)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn ctrl(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::CONTROL)
+    }
+
+    fn shift(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::SHIFT)
+    }
+
+    #[test]
+    fn test_text_area__insert_in_middle() {
+        let mut state = TextAreaState::single_line("helo");
+        state.handle_input(key(KeyCode::Left));
+        state.handle_input(key(KeyCode::Char('l')));
+        assert_eq!(state.text(), "hello");
+    }
+
+    #[test]
+    fn test_text_area__backspace_grapheme() {
+        // `é` here is an `e` followed by a combining accent.
+        let mut state = TextAreaState::single_line("cafe\u{301}");
+        state.handle_input(key(KeyCode::Backspace));
+        assert_eq!(state.text(), "caf");
+    }
+
+    #[test]
+    fn test_text_area__backspace_at_start() {
+        let mut state = TextAreaState::single_line("abc");
+        state.handle_input(key(KeyCode::Home));
+        assert!(!state.handle_input(key(KeyCode::Backspace)));
+        assert_eq!(state.text(), "abc");
+        state.handle_input(key(KeyCode::End));
+        assert!(!state.handle_input(key(KeyCode::Delete)));
+        assert!(state.handle_input(key(KeyCode::Backspace)));
+    }
+
+    #[test]
+    fn test_text_area__word_motion() {
+        let mut state = TextAreaState::single_line("one two three");
+        state.handle_input(ctrl(KeyCode::Left));
+        state.handle_input(ctrl(KeyCode::Left));
+        state.handle_input(key(KeyCode::Char('X')));
+        assert_eq!(state.text(), "one Xtwo three");
+        state.handle_input(ctrl(KeyCode::Right));
+        state.handle_input(key(KeyCode::Char('Y')));
+        assert_eq!(state.text(), "one XtwoY three");
+    }
+
+    #[test]
+    fn test_text_area__ctrl_w() {
+        let mut state = TextAreaState::single_line("one two  ");
+        state.handle_input(ctrl(KeyCode::Char('w')));
+        assert_eq!(state.text(), "one ");
+    }
+
+    #[test]
+    fn test_text_area__ctrl_u() {
+        let mut state = TextAreaState::multi_line("first\nsecond line");
+        state.handle_input(ctrl(KeyCode::Char('u')));
+        assert_eq!(state.text(), "first\n");
+    }
+
+    #[test]
+    fn test_text_area__home_end() {
+        let mut state = TextAreaState::multi_line("first\nsecond");
+        state.handle_input(key(KeyCode::Home));
+        state.handle_input(key(KeyCode::Char('>')));
+        state.handle_input(key(KeyCode::End));
+        state.handle_input(key(KeyCode::Char('<')));
+        assert_eq!(state.text(), "first\n>second<");
+    }
+
+    #[test]
+    fn test_text_area__selection_replaced() {
+        let mut state = TextAreaState::single_line("hello world");
+        state.handle_input(shift(KeyCode::Left));
+        state.handle_input(shift(KeyCode::Left));
+        state.handle_input(key(KeyCode::Char('X')));
+        assert_eq!(state.text(), "hello worX");
+    }
+
+    #[test]
+    fn test_text_area__paste_single_line() {
+        let mut state = TextAreaState::single_line("");
+        state.paste("one\r\ntwo");
+        assert_eq!(state.text(), "one two");
+    }
+
+    #[test]
+    fn test_text_area__up_down() {
+        let mut state = TextAreaState::multi_line("abc\nde");
+        state.handle_input(key(KeyCode::Up));
+        state.handle_input(key(KeyCode::Char('X')));
+        assert_eq!(state.text(), "abXc\nde");
+        state.handle_input(key(KeyCode::Down));
+        state.handle_input(key(KeyCode::Char('Y')));
+        assert_eq!(state.text(), "abXc\ndeY");
+    }
+
+    #[test]
+    fn test_list_hitbox__index_at() {
+        let mut state = widgets::ListState::default();
+        state.select(Some(4));
+        let mut hitbox = ListHitbox::default();
+        // Three rows inside the borders, so the list scrolls to show item 4 last.
+        hitbox.update(layout::Rect::new(0, 0, 10, 5), &state, vec![1; 6]);
+        assert_eq!(hitbox.index_at(1, 1), Some(2));
+        assert_eq!(hitbox.index_at(1, 3), Some(4));
+        assert_eq!(hitbox.index_at(0, 1), None);
+        assert_eq!(hitbox.index_at(1, 4), None);
+    }
+
+    #[test]
+    fn test_selectable_list__stays_in_bounds() {
+        let mut list = SelectableList::default();
+        list.select_first(0);
+        list.go_down(0);
+        assert_eq!(list.selected(), None);
+
+        list.select_first(2);
+        list.go_up();
+        assert_eq!(list.selected(), Some(0));
+        list.go_down(2);
+        list.go_down(2);
+        assert_eq!(list.selected(), Some(1));
+    }
 }